@@ -1,18 +1,21 @@
 use std::{
     cmp,
     convert::TryInto,
-    io::{self, Read, Write},
+    io::{self, IoSlice, Read, Write},
     process,
     sync::{Arc, Mutex},
     thread,
 };
 
-use rand::Rng;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
 use sled_workload_random_ops::*;
 
 const DEFAULT_OP_COUNT: usize = 50;
 
+// Directory holding the compressed per-generation op-history segments.
+const HISTORY_DIR: &str = "history_dir";
+
 // This workload performs a variety of operations on a tree, records those operations in a
 // reference data structure, and also prints information about the operations to standard output.
 // The checker will read the operations, reconstruct the same reference data structure, and verify
@@ -39,6 +42,7 @@ impl FileDescriptor {
 
 impl Read for FileDescriptor {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        fault_injection::maybe_fail_random()?;
         let rv = unsafe {
             libc::read(
                 self.fd,
@@ -55,6 +59,7 @@ impl Read for FileDescriptor {
 
 impl Write for FileDescriptor {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        fault_injection::maybe_fail_random()?;
         let rv = unsafe {
             libc::write(
                 self.fd,
@@ -68,11 +73,101 @@ impl Write for FileDescriptor {
         Ok(rv as usize)
     }
 
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        fault_injection::maybe_fail_random()?;
+        // IoSlice is guaranteed ABI-compatible with `struct iovec`, so the slice
+        // can be handed straight to `writev` without copying.
+        let rv = unsafe {
+            libc::writev(
+                self.fd,
+                bufs.as_ptr() as *const libc::iovec,
+                cmp::min(bufs.len(), libc::c_int::MAX as usize) as libc::c_int,
+            )
+        };
+        if rv == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(rv as usize)
+    }
+
     fn flush(&mut self) -> io::Result<()> {
         Ok(())
     }
 }
 
+// Coalesced, newline-framed op log. Encoded ops accumulate in memory and are
+// emitted with a single `writev` per flush (one `iovec` per pending op),
+// replacing the two `write_all` syscalls per op that the old `send_op!` macro
+// issued. The buffer is flushed when it grows past `FLUSH_THRESHOLD` and
+// explicitly at every durability/crash boundary (`Flush`, `Restart`, arming the
+// SIGKILL timer, and teardown), so no op is ever stranded in memory when a
+// crash could land.
+const FLUSH_THRESHOLD: usize = 64 * 1024;
+
+struct BufferedOpLog {
+    pending: Vec<Vec<u8>>,
+    pending_bytes: usize,
+    write_fd: Option<FileDescriptor>,
+    stdout: io::Stdout,
+}
+
+impl BufferedOpLog {
+    fn new(write_fd: Option<FileDescriptor>) -> BufferedOpLog {
+        BufferedOpLog {
+            pending: Vec::new(),
+            pending_bytes: 0,
+            write_fd,
+            stdout: io::stdout(),
+        }
+    }
+
+    fn push(&mut self, mut encoded: Vec<u8>) -> io::Result<()> {
+        encoded.push(b'\n');
+        self.pending_bytes += encoded.len();
+        self.pending.push(encoded);
+        if self.pending_bytes >= FLUSH_THRESHOLD {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        if let Some(ref mut write_fd) = self.write_fd {
+            write_coalesced(write_fd, &self.pending)?;
+        }
+        let mut stdout_lock = self.stdout.lock();
+        write_coalesced(&mut stdout_lock, &self.pending)?;
+        self.pending.clear();
+        self.pending_bytes = 0;
+        Ok(())
+    }
+}
+
+// Write every pending op in a single vectored call, finishing any tail left by a
+// short `writev` with a flat `write_all` so the framing stays intact.
+fn write_coalesced<W: Write>(writer: &mut W, pending: &[Vec<u8>]) -> io::Result<()> {
+    let total: usize = pending.iter().map(|buf| buf.len()).sum();
+    let slices: Vec<IoSlice> = pending.iter().map(|buf| IoSlice::new(buf)).collect();
+    let written = writer.write_vectored(&slices)?;
+    if written < total {
+        let mut skip = written;
+        let mut tail = Vec::with_capacity(total - written);
+        for buf in pending {
+            if skip >= buf.len() {
+                skip -= buf.len();
+                continue;
+            }
+            tail.extend_from_slice(&buf[skip..]);
+            skip = 0;
+        }
+        writer.write_all(&tail)?;
+    }
+    Ok(())
+}
+
 #[derive(Default)]
 struct Pipe {
     read_fd: Option<libc::c_int>,
@@ -138,6 +233,7 @@ impl RandomOpsPipes {
 }
 
 fn main() {
+    raise_fd_limit();
     let matches = App::new("random_ops_workload")
         .version(crate_version!())
         .arg(
@@ -158,6 +254,40 @@ fn main() {
                 .short("f")
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("minimize")
+                .long("minimize")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .required(false)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("print_seed")
+                .long("print-seed")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("fault_rate")
+                .long("fault-rate")
+                .required(false)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("value_shape")
+                .long("value-shape")
+                .required(false)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("value_scale")
+                .long("value-scale")
+                .required(false)
+                .takes_value(true),
+        )
         .get_matches();
     let op_count = if let Some(op_count) = matches.value_of("op_count") {
         if let Ok(op_count) = op_count.parse() {
@@ -171,6 +301,78 @@ fn main() {
     };
     let crash = matches.is_present("crash");
     let flusher = matches.is_present("flusher");
+    let value_shape = match matches.value_of("value_shape") {
+        Some(shape) => shape.parse().unwrap_or_else(|_| {
+            eprintln!("{}", matches.usage());
+            process::exit(1);
+        }),
+        None => DEFAULT_VALUE_SHAPE,
+    };
+    let value_scale = match matches.value_of("value_scale") {
+        Some(scale) => scale.parse().unwrap_or_else(|_| {
+            eprintln!("{}", matches.usage());
+            process::exit(1);
+        }),
+        None => DEFAULT_VALUE_SCALE,
+    };
+    // Arm probabilistic I/O fault injection: each raw read/write on a pipe then
+    // has this chance of failing with EIO, which the workload treats as a crash
+    // point (see `run`). Defaults to zero (disabled).
+    let fault_rate: f64 = if let Some(fault_rate) = matches.value_of("fault_rate") {
+        if let Ok(fault_rate) = fault_rate.parse() {
+            fault_rate
+        } else {
+            eprintln!("{}", matches.usage());
+            process::exit(1);
+        }
+    } else {
+        0.0
+    };
+    fault_injection::set_fault_rate(fault_rate);
+    // A caller-supplied seed makes a run reproducible; otherwise pick one at
+    // random. Either way log the seed and the exact command line that replays
+    // this run, so a failure recorded in CI can be reproduced directly (the
+    // `--print-seed` flag is accepted for symmetry but the seed is always
+    // logged).
+    let seed: u64 = if let Some(seed) = matches.value_of("seed") {
+        if let Ok(seed) = seed.parse() {
+            seed
+        } else {
+            eprintln!("{}", matches.usage());
+            process::exit(1);
+        }
+    } else {
+        rand::thread_rng().gen()
+    };
+    eprintln!(
+        "seed={} (replay with: random_ops_workload {}{}{} --value-shape {} --value-scale {} \
+        --fault-rate {} --seed {})",
+        seed,
+        op_count,
+        if crash { " --crash" } else { "" },
+        if flusher { " --flusher" } else { "" },
+        value_shape,
+        value_scale,
+        fault_rate,
+        seed,
+    );
+
+    // When minimizing, read a previously recorded op history from standard
+    // input, shrink it to the smallest sequence that still reproduces the
+    // verification failure, and print that reproducer. No forking happens in
+    // this mode; each candidate is replayed directly against a fresh database.
+    if matches.is_present("minimize") {
+        let history: Vec<Op> = OpReader::new(io::stdin()).map(Result::unwrap).collect();
+        let minimized = minimize(&history, flusher);
+        let stdout = io::stdout();
+        let mut stdout_lock = stdout.lock();
+        for op in &minimized {
+            let mut encoded = op.encode();
+            encoded.push(b'\n');
+            stdout_lock.write_all(&encoded).unwrap();
+        }
+        return;
+    }
 
     // The pipe FDs will be modified from the setup and teardown hooks, taking advantage of
     // Mutex's interior mutability. After forking, each process will close the FDs it doesn't
@@ -178,9 +380,14 @@ fn main() {
     // the process will fork and the argument will be passed to run in the child process. At
     // that point, the child process could lock its mutex forever, as it is operating on different
     // memory at that point.
+    // The op history is backed by a series of append-only, zstd-compressed
+    // segments on disk (one per fork generation, keyed by log sequence number)
+    // rather than an in-memory Vec, so memory stays flat no matter how long the
+    // crash loop runs. Start from a clean slate.
+    let _ = std::fs::remove_dir_all(HISTORY_DIR);
     let pipes = Arc::new(Mutex::new(RandomOpsPipes::default()));
     let io_thread_join_handle = Arc::new(Mutex::new(None));
-    let history: Arc<Mutex<Vec<Op>>> = Arc::new(Mutex::new(Vec::new()));
+    let lsn = Arc::new(Mutex::new(0u64));
 
     crash_recovery_loop_with_hooks(
         || {
@@ -195,31 +402,38 @@ fn main() {
             pipes_guard.history.close_read().unwrap();
             let operations_reader = pipes_guard.operations.reader();
 
-            let history_copy = history.lock().unwrap().clone();
-
-            // Start thread to listen on the pipe for new operations and record them
+            // Allocate a fresh log sequence number for this generation's segment.
+            let generation = {
+                let mut lsn_guard = lsn.lock().unwrap();
+                let generation = *lsn_guard;
+                *lsn_guard += 1;
+                generation
+            };
+
+            // Start thread to listen on the pipe for new operations and append
+            // them to this generation's compressed segment. The segment's zstd
+            // frame is finalized when the writer is dropped as the thread exits.
             let mut io_handle_guard = io_thread_join_handle.lock().unwrap();
             assert!(io_handle_guard.is_none());
             {
                 let pipes = pipes.clone();
-                let history = history.clone();
                 *io_handle_guard = Some(thread::spawn(move || {
-                    let mut history_guard = history.lock().unwrap();
+                    let mut segment =
+                        history::HistorySegmentWriter::create(HISTORY_DIR, generation).unwrap();
                     for res in OpReader::new(operations_reader) {
                         let op = res.unwrap();
-                        history_guard.push(op);
+                        segment.append_line(&op.encode()).unwrap();
                     }
+                    segment.flush().unwrap();
                     pipes.lock().unwrap().operations.close_read().unwrap();
                 }));
             }
 
-            // Send history of operations thus far to new child process
+            // Stream the history of operations thus far to the new child process
+            // by decompressing every prior segment straight into the pipe.
             let mut history_writer = pipes_guard.history.writer();
-            for op in history_copy {
-                let mut encoded = op.encode();
-                encoded.push(b'\n');
-                history_writer.write_all(&encoded).unwrap();
-            }
+            let mut history_reader = history::HistoryReader::new(HISTORY_DIR, generation);
+            io::copy(&mut history_reader, &mut history_writer).unwrap();
             pipes_guard.history.close_write().unwrap();
         },
         || {
@@ -232,16 +446,51 @@ fn main() {
                 .join()
                 .unwrap();
         },
-        (pipes.clone(), op_count, flusher),
+        (pipes.clone(), op_count, flusher, seed, value_shape, value_scale),
         crash,
     );
 }
 
-fn run(args: (Arc<Mutex<RandomOpsPipes>>, usize, bool), crash: bool) -> Result<(), sled::Error> {
-    let mut rng = rand::thread_rng();
+// Returns true if `error` is an injected fault (an EIO I/O error raised while
+// probabilistic fault injection is armed) rather than a genuine failure.
+fn is_injected_fault(error: &sled::Error) -> bool {
+    if fault_injection::fault_rate() <= 0.0 {
+        return false;
+    }
+    matches!(error, sled::Error::Io(e) if e.raw_os_error() == Some(libc::EIO))
+}
+
+fn run(
+    args: (Arc<Mutex<RandomOpsPipes>>, usize, bool, u64, f64, f64),
+    crash: bool,
+) -> Result<(), sled::Error> {
+    let pipes = args.0.clone();
+    match run_workload(args, crash) {
+        Err(ref e) if crash && is_injected_fault(e) => {
+            // A synthetic I/O fault is a legitimate crash point: flush nothing
+            // further, close the operations pipe so the parent stops reading,
+            // and SIGKILL ourselves so the crash loop recovers and continues,
+            // exactly as it would after a `DelayedCrash`.
+            eprintln!("injected I/O fault, treating as crash point");
+            let _ = pipes.lock().unwrap().operations.close_write();
+            unsafe {
+                libc::raise(libc::SIGKILL);
+            }
+            unreachable!()
+        }
+        result => result,
+    }
+}
+
+fn run_workload(
+    args: (Arc<Mutex<RandomOpsPipes>>, usize, bool, u64, f64, f64),
+    crash: bool,
+) -> Result<(), sled::Error> {
+    let (pipes, op_count, flusher, seed, value_shape, value_scale) = args;
+    let mut rng = StdRng::seed_from_u64(seed);
+    let value_sizer = ValueSizer::new(value_shape, value_scale);
     let mut history = Vec::new();
     let mut history_op_count = 0;
-    let (pipes, op_count, flusher) = args;
     let mut pipes_guard = pipes.lock().unwrap();
     if crash {
         pipes_guard.operations.close_read()?;
@@ -255,8 +504,13 @@ fn run(args: (Arc<Mutex<RandomOpsPipes>>, usize, bool), crash: bool) -> Result<(
                 | Op::Batched(_)
                 | Op::Restart
                 | Op::Flush
-                | Op::DelayedCrash => history_op_count += 1,
-                Op::CrashAndRecoveryVirtualOp(_) | Op::IdResultVirtualOp(_) => {}
+                | Op::DelayedCrash
+                | Op::Range(_, _, _)
+                | Op::Cas { .. }
+                | Op::FailPoint(_, _) => history_op_count += 1,
+                Op::CrashAndRecoveryVirtualOp(_)
+                | Op::IdResultVirtualOp(_)
+                | Op::CasResultVirtualOp(_) => {}
             }
             history.push(op);
         }
@@ -265,28 +519,26 @@ fn run(args: (Arc<Mutex<RandomOpsPipes>>, usize, bool), crash: bool) -> Result<(
     // wait for previous crashed process's file lock to be released
     block_on_database_lock(WORKLOAD_DIR)?;
 
-    let crash_during_initialization = rand::thread_rng().gen_bool(0.1);
+    let crash_during_initialization = rng.gen_bool(0.1);
     let mut timer_running = false;
     if crash && crash_during_initialization {
         start_sigkill_timer();
         timer_running = true;
     }
 
-    let mut write_fd = if crash {
+    let write_fd = if crash {
         Some(pipes_guard.operations.writer())
     } else {
         None
     };
-    let stdout = io::stdout();
-    let mut stdout_lock = stdout.lock();
+    let mut op_log = BufferedOpLog::new(write_fd);
     macro_rules! send_op {
         ($op: expr) => {
-            let mut encoded = $op.encode();
-            encoded.push(b'\n');
-            if let Some(ref mut write_fd) = write_fd {
-                write_fd.write_all(&encoded)?;
+            op_log.push($op.encode())?;
+            if timer_running {
+                // a SIGKILL may land at any moment; put the op on the wire now
+                op_log.flush()?;
             }
-            stdout_lock.write_all(&encoded)?;
             history.push($op.clone());
         };
     }
@@ -300,6 +552,11 @@ fn run(args: (Arc<Mutex<RandomOpsPipes>>, usize, bool), crash: bool) -> Result<(
     };
     let virtual_op = Op::CrashAndRecoveryVirtualOp(stable_batch);
     send_op!(virtual_op);
+    if timer_running {
+        // the crash timer is already armed; make sure the recovery marker is on
+        // the wire before SIGKILL can land
+        op_log.flush()?;
+    }
     let mut reference = verify_against_ops(&db, &history)?;
 
     for _ in history_op_count..op_count {
@@ -311,7 +568,7 @@ fn run(args: (Arc<Mutex<RandomOpsPipes>>, usize, bool), crash: bool) -> Result<(
                 send_op!(op);
                 db.insert(
                     &u16::to_be_bytes(reference.set_counter),
-                    value_factory(reference.set_counter),
+                    value_factory(reference.set_counter, value_sizer.sample(&mut rng)),
                 )?;
             }
             Op::Del(key) => {
@@ -327,29 +584,45 @@ fn run(args: (Arc<Mutex<RandomOpsPipes>>, usize, bool), crash: bool) -> Result<(
             }
             Op::Batched(ref batch_ops) => {
                 send_op!(op);
-                let mut batch = sled::Batch::default();
-                batch.insert(
-                    BATCH_COUNTER_KEY,
-                    reference.batch_counter.to_be_bytes().to_vec(),
-                );
-                for batch_op in batch_ops {
-                    match batch_op {
-                        BatchOp::Set => {
-                            batch.insert(
-                                u16::to_be_bytes(saved_set_counter).to_vec(),
-                                value_factory(saved_set_counter),
-                            );
-                            saved_set_counter += 1;
-                        }
-                        BatchOp::Del(key) => {
-                            batch.remove(u16::to_be_bytes((*key).into()).to_vec());
+                // A batch carrying a `Cas` is a multi-key transaction that commits
+                // atomically only if every precondition holds; a blind batch stays
+                // an unconditional `apply_batch`.
+                if batch_ops.iter().any(|op| matches!(op, BatchOp::Cas { .. })) {
+                    apply_transactional_batch(
+                        &db,
+                        batch_ops,
+                        reference.batch_counter,
+                        saved_set_counter,
+                        &value_sizer,
+                        &mut rng,
+                    )?;
+                } else {
+                    let mut batch = sled::Batch::default();
+                    batch.insert(
+                        BATCH_COUNTER_KEY,
+                        reference.batch_counter.to_be_bytes().to_vec(),
+                    );
+                    for batch_op in batch_ops {
+                        match batch_op {
+                            BatchOp::Set => {
+                                batch.insert(
+                                    u16::to_be_bytes(saved_set_counter).to_vec(),
+                                    value_factory(saved_set_counter, value_sizer.sample(&mut rng)),
+                                );
+                                saved_set_counter += 1;
+                            }
+                            BatchOp::Del(key) => {
+                                batch.remove(u16::to_be_bytes((*key).into()).to_vec());
+                            }
+                            BatchOp::Cas { .. } => unreachable!(),
                         }
                     }
+                    db.apply_batch(batch)?;
                 }
-                db.apply_batch(batch)?;
             }
             Op::Restart => {
                 send_op!(op);
+                op_log.flush()?;
                 drop(db);
                 block_on_database_lock(WORKLOAD_DIR)?;
                 db = db_config.open()?;
@@ -358,6 +631,7 @@ fn run(args: (Arc<Mutex<RandomOpsPipes>>, usize, bool), crash: bool) -> Result<(
             Op::Flush => {
                 db.flush()?;
                 send_op!(op);
+                op_log.flush()?;
             }
             Op::DelayedCrash => {
                 send_op!(op);
@@ -365,15 +639,296 @@ fn run(args: (Arc<Mutex<RandomOpsPipes>>, usize, bool), crash: bool) -> Result<(
                     start_sigkill_timer();
                     timer_running = true;
                 }
+                // force every buffered op onto the wire before the timer fires
+                op_log.flush()?;
+            }
+            Op::Range(lo, hi, reverse) => {
+                send_op!(op);
+                // Read-side assertion: the reference already reflects every op up
+                // to this point, so verify the bounded scan against it in place.
+                verify_range_against_reference(&db, &reference, (lo, hi), reverse)?;
+            }
+            Op::FailPoint(ref name, count) => {
+                send_op!(op);
+                // Arm the named sled failpoint to fire (return an error) after
+                // being skipped `count` times, tearing the write path at exactly
+                // this site. Keep a SIGKILL timer armed as a backstop in case the
+                // site is never reached before the run ends.
+                fail::cfg(name.clone(), &format!("{}*off->return", count)).unwrap();
+                if crash && !timer_running {
+                    start_sigkill_timer();
+                    timer_running = true;
+                }
+                op_log.flush()?;
             }
-            Op::CrashAndRecoveryVirtualOp(_) | Op::IdResultVirtualOp(_) => unreachable!(),
+            Op::Cas { key, old, new } => {
+                send_op!(op);
+                // Read the real `compare_and_swap` outcome and feed it back as a
+                // virtual op so the reference can resolve a crash-straddling swap,
+                // exactly as `Op::Id` feeds back its generated id.
+                let succeeded = apply_cas(&db, key, old, new, &value_sizer, &mut rng)?;
+                let virtual_op = Op::CasResultVirtualOp(succeeded);
+                reference.update_before(&virtual_op);
+                send_op!(virtual_op);
+            }
+            Op::CrashAndRecoveryVirtualOp(_)
+            | Op::IdResultVirtualOp(_)
+            | Op::CasResultVirtualOp(_) => unreachable!(),
         }
         reference.update_after(&op);
     }
 
+    op_log.flush()?;
     if crash {
         pipes_guard.operations.close_write()?;
     }
 
     Ok(())
 }
+
+// Execute a single compare-and-swap against the tree and report whether it
+// succeeded. A present precondition (`old == Some`) is compared against the
+// value the key currently holds — its length was drawn from a distribution and
+// is not reconstructable — so the swap turns on presence and the counter the
+// value encodes, never its size.
+fn apply_cas<R: Rng>(
+    db: &sled::Db,
+    key: u16,
+    old: Option<u16>,
+    new: Option<u16>,
+    value_sizer: &ValueSizer,
+    rng: &mut R,
+) -> Result<bool, sled::Error> {
+    let key_bytes = u16::to_be_bytes(key);
+    let old_arg: Option<Vec<u8>> = match old {
+        Some(expected) => Some(match db.get(key_bytes)? {
+            Some(existing) => existing.to_vec(),
+            // Absent where a present value was expected: a minimal encoding of
+            // the expected counter can never equal "no value", so the swap fails.
+            None => value_factory(expected, 2),
+        }),
+        None => None,
+    };
+    let new_arg = new.map(|value| value_factory(value, value_sizer.sample(rng)));
+    Ok(db.compare_and_swap(key_bytes, old_arg, new_arg)?.is_ok())
+}
+
+// Execute a transactional (multi-key) batch: every op is applied atomically, and
+// the whole transaction aborts if any `Cas` precondition fails to match the
+// value the key holds at that point. Values are drawn up front so the retryable
+// transaction closure never touches the rng.
+fn apply_transactional_batch<R: Rng>(
+    db: &sled::Db,
+    batch_ops: &[BatchOp],
+    batch_counter: u32,
+    start_set_counter: u16,
+    value_sizer: &ValueSizer,
+    rng: &mut R,
+) -> Result<(), sled::Error> {
+    use sled::transaction::{ConflictableTransactionError, TransactionError};
+
+    enum Prepared {
+        Set { key: [u8; 2], value: Vec<u8> },
+        Del { key: [u8; 2] },
+        Cas {
+            key: [u8; 2],
+            old: Option<u16>,
+            new: Option<Vec<u8>>,
+        },
+    }
+
+    let mut prepared = Vec::with_capacity(batch_ops.len());
+    let mut set_counter = start_set_counter;
+    for batch_op in batch_ops {
+        match batch_op {
+            BatchOp::Set => {
+                prepared.push(Prepared::Set {
+                    key: u16::to_be_bytes(set_counter),
+                    value: value_factory(set_counter, value_sizer.sample(rng)),
+                });
+                set_counter += 1;
+            }
+            BatchOp::Del(key) => prepared.push(Prepared::Del {
+                key: u16::to_be_bytes((*key).into()),
+            }),
+            BatchOp::Cas { key, old, new } => prepared.push(Prepared::Cas {
+                key: u16::to_be_bytes(*key),
+                old: *old,
+                new: new.map(|value| value_factory(value, value_sizer.sample(rng))),
+            }),
+        }
+    }
+
+    let counter_value = batch_counter.to_be_bytes().to_vec();
+    let result: Result<(), TransactionError<()>> = db.transaction(|tree| {
+        tree.insert(BATCH_COUNTER_KEY, counter_value.clone())?;
+        for op in &prepared {
+            match op {
+                Prepared::Set { key, value } => {
+                    tree.insert(&key[..], value.clone())?;
+                }
+                Prepared::Del { key } => {
+                    tree.remove(&key[..])?;
+                }
+                Prepared::Cas { key, old, new } => {
+                    let current = tree.get(&key[..])?;
+                    let matches = match old {
+                        Some(expected) => current
+                            .as_ref()
+                            .map(|bytes| verify_value(bytes).ok() == Some(*expected))
+                            .unwrap_or(false),
+                        None => current.is_none(),
+                    };
+                    if !matches {
+                        return Err(ConflictableTransactionError::Abort(()));
+                    }
+                    match new {
+                        Some(bytes) => {
+                            tree.insert(&key[..], bytes.clone())?;
+                        }
+                        None => {
+                            tree.remove(&key[..])?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    });
+    match result {
+        // A committed transaction and a clean abort are both expected outcomes.
+        Ok(()) | Err(TransactionError::Abort(())) => Ok(()),
+        Err(TransactionError::Storage(e)) => Err(e),
+    }
+}
+
+// Non-forking variant of `run` used by the shrinker: replay a fixed op history
+// against a fresh database and verify the result. Unlike `run` it generates no
+// new ops, arms no SIGKILL timer, and reads no pipes; the recorded virtual ops
+// drive the reconstructed reference directly. The reference (and with it the
+// set/batch counters) is rebuilt from scratch, so candidates with ops removed
+// don't desync the counters used to compute keys.
+fn replay(history: &[Op], flusher: bool) -> Result<(), sled::Error> {
+    let _ = std::fs::remove_dir_all(WORKLOAD_DIR);
+    let db_config =
+        config(WORKLOAD_DIR, CACHE_CAPACITY, SEGMENT_SIZE, flusher).idgen_persist_interval(1);
+    let db_config = &db_config;
+    let mut db = db_config.open()?;
+    let mut reference = Reference::new();
+    // The value sizer is self-describing on disk, so a fixed seed here is fine:
+    // verification reconstructs each payload from the length it reads back.
+    let value_sizer = ValueSizer::default();
+    let mut rng = StdRng::seed_from_u64(0);
+
+    for op in history {
+        let mut saved_set_counter = reference.set_counter;
+        reference.update_before(op);
+        match op {
+            Op::Set => {
+                db.insert(
+                    &u16::to_be_bytes(reference.set_counter),
+                    value_factory(reference.set_counter, value_sizer.sample(&mut rng)),
+                )?;
+            }
+            Op::Del(key) => {
+                db.remove(&*vec![0, *key])?;
+            }
+            Op::Id => {
+                // The recorded IdResultVirtualOp that follows drives the
+                // reference; the freshly generated id is irrelevant here.
+                db.generate_id()?;
+            }
+            Op::Batched(batch_ops) => {
+                if batch_ops.iter().any(|op| matches!(op, BatchOp::Cas { .. })) {
+                    apply_transactional_batch(
+                        &db,
+                        batch_ops,
+                        reference.batch_counter,
+                        saved_set_counter,
+                        &value_sizer,
+                        &mut rng,
+                    )?;
+                } else {
+                    let mut batch = sled::Batch::default();
+                    batch.insert(
+                        BATCH_COUNTER_KEY,
+                        reference.batch_counter.to_be_bytes().to_vec(),
+                    );
+                    for batch_op in batch_ops {
+                        match batch_op {
+                            BatchOp::Set => {
+                                batch.insert(
+                                    u16::to_be_bytes(saved_set_counter).to_vec(),
+                                    value_factory(saved_set_counter, value_sizer.sample(&mut rng)),
+                                );
+                                saved_set_counter += 1;
+                            }
+                            BatchOp::Del(key) => {
+                                batch.remove(u16::to_be_bytes((*key).into()).to_vec());
+                            }
+                            BatchOp::Cas { .. } => unreachable!(),
+                        }
+                    }
+                    db.apply_batch(batch)?;
+                }
+            }
+            Op::Restart => {
+                drop(db);
+                block_on_database_lock(WORKLOAD_DIR)?;
+                db = db_config.open()?;
+                verify_against_reference(&db, &mut reference)?;
+            }
+            Op::Flush => {
+                db.flush()?;
+            }
+            Op::DelayedCrash => {}
+            Op::Range(lo, hi, reverse) => {
+                verify_range_against_reference(&db, &reference, (*lo, *hi), *reverse)?;
+            }
+            Op::Cas { key, old, new } => {
+                // The recorded CasResultVirtualOp that follows drives the
+                // reference; the swap is still applied to keep the tree in step.
+                apply_cas(&db, *key, *old, *new, &value_sizer, &mut rng)?;
+            }
+            // The reference already applied the crash-boundary semantics in
+            // `update_before`; replaying injects no real fault.
+            Op::FailPoint(_, _) => {}
+            Op::CrashAndRecoveryVirtualOp(_)
+            | Op::IdResultVirtualOp(_)
+            | Op::CasResultVirtualOp(_) => {}
+        }
+        reference.update_after(op);
+    }
+
+    verify_against_reference(&db, &mut reference)
+}
+
+// Replay `history` and report whether it still triggers the failure, treating
+// both a returned error and a verification panic as a reproduction.
+fn reproduces_failure(history: &[Op], flusher: bool) -> bool {
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        replay(history, flusher)
+    }));
+    matches!(result, Ok(Err(_)) | Err(_))
+}
+
+// Fixed-point shrinking loop: repeatedly apply `shrink` and accept the first
+// strictly smaller candidate that still reproduces the failure, until no
+// single removal helps any more.
+fn minimize(history: &[Op], flusher: bool) -> Vec<Op> {
+    let mut smallest = history.to_vec();
+    loop {
+        let mut improved = false;
+        for candidate in shrink(&smallest) {
+            if candidate.len() < smallest.len() && reproduces_failure(&candidate, flusher) {
+                smallest = candidate;
+                improved = true;
+                break;
+            }
+        }
+        if !improved {
+            break;
+        }
+    }
+    smallest
+}
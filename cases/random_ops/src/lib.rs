@@ -1,9 +1,12 @@
 use std::{
     collections::BTreeMap,
     io::{self, BufRead, BufReader, Read},
+    ops::{Bound, RangeBounds},
 };
 
+use quickcheck::{empty_shrinker, Arbitrary, Gen};
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 pub use common_utils::*;
 
@@ -11,7 +14,7 @@ pub const SEGMENT_SIZE: usize = 256;
 pub const CACHE_CAPACITY: usize = 256;
 pub const BATCH_COUNTER_KEY: &[u8] = b"batch_counter";
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Op {
     Set,
     Del(u8),
@@ -20,28 +23,110 @@ pub enum Op {
     Restart,
     Flush,
     DelayedCrash,
+    /// Arm a named failpoint in alice-sled's IO/log layer (see
+    /// [`FAILPOINT_SITES`]) that fires after being skipped `u64` times, tearing
+    /// the write path at a specific site instead of at an arbitrary moment like
+    /// [`Op::DelayedCrash`]. A fired failpoint is treated as a crash boundary.
+    FailPoint(String, u64),
+    /// Scan a bounded key range and verify it against the reference, forward
+    /// when the flag is `false` and reversed when `true`. Unlike the write ops
+    /// this performs no mutation — it is a read-side assertion woven into the
+    /// history so generated workloads exercise `range(lo..hi)` (and its
+    /// `DoubleEndedIterator`) rather than only full-tree iteration.
+    Range(Bound<u16>, Bound<u16>, bool),
+    /// Atomic compare-and-swap of a single key: replace the value of `key` with
+    /// `new` only if its current value equals `old` (`None` on either side means
+    /// "absent"). Unlike the blind [`Op::Set`]/[`Op::Del`] writes this models a
+    /// *conditional* update, so the reference can catch lost-update and
+    /// phantom-success bugs in the `compare_and_swap` path. The genuine outcome
+    /// is recorded by the [`Op::CasResultVirtualOp`] that follows it, since a
+    /// crash can hide whether the swap actually landed.
+    Cas {
+        key: u16,
+        old: Option<u16>,
+        new: Option<u16>,
+    },
     CrashAndRecoveryVirtualOp(u32),
     IdResultVirtualOp(u64),
+    /// The observed result of the preceding [`Op::Cas`]: `true` if the tree's
+    /// `compare_and_swap` reported success, `false` if it reported a mismatch.
+    /// Replayed from the recorded history (never generated directly), it resolves
+    /// the ambiguous case where the model cannot tell whether a crash-straddling
+    /// swap committed, exactly as [`Op::IdResultVirtualOp`] pins down a generated
+    /// id.
+    CasResultVirtualOp(bool),
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// The named injection sites in alice-sled's IO/log layer a [`Op::FailPoint`] can
+/// arm, mirroring the failpoints sled exercises in its own failure tests. Pinning
+/// the crash to one of these tells a failing seed *which* write-path operation
+/// produced the torn state.
+pub const FAILPOINT_SITES: &[&str] = &[
+    "buffer write",
+    "snap write crc",
+    "segment initial free zero",
+    "zero garbage segment",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BatchOp {
     Set,
     Del(u8),
+    /// The transactional counterpart of [`Op::Cas`]: within an [`Op::Batched`]
+    /// this turns the batch into an all-or-nothing transaction that commits only
+    /// if every `Cas` precondition (current value of `key` equals `old`) holds,
+    /// modelling multi-key compare-and-swap.
+    Cas {
+        key: u16,
+        old: Option<u16>,
+        new: Option<u16>,
+    },
 }
 
 #[derive(Debug)]
 pub struct OpDecodeError;
 
+/// Append the wire form of a single `Range` endpoint: `u` for unbounded, or
+/// `i`/`e` (included/excluded) followed by the decimal endpoint.
+fn encode_bound(output: &mut Vec<u8>, bound: &Bound<u16>) {
+    match bound {
+        Bound::Unbounded => output.push(b'u'),
+        Bound::Included(value) => output.extend_from_slice(format!("i{}", value).as_bytes()),
+        Bound::Excluded(value) => output.extend_from_slice(format!("e{}", value).as_bytes()),
+    }
+}
+
+/// Render one compare-and-swap operand: `n` for `None`, or the decimal counter.
+fn cas_operand(value: &Option<u16>) -> String {
+    match value {
+        Some(v) => v.to_string(),
+        None => "n".to_string(),
+    }
+}
+
+/// Pick a compare-and-swap key (one of the deletable `0..=255` keys) along with
+/// its `old` precondition and `new` outcome. A present value always encodes its
+/// own key, so a `Some` operand is always that key, keeping the key<->value
+/// cross-check in [`check_value_against_key`] intact.
+fn cas_operands<R: Rng>(rng: &mut R) -> (u16, Option<u16>, Option<u16>) {
+    let key = u16::from(rng.gen::<u8>());
+    let present_or_absent = |rng: &mut R| if rng.gen() { Some(key) } else { None };
+    (key, present_or_absent(rng), present_or_absent(rng))
+}
+
 impl Op {
     pub fn generate<R: Rng>(rng: &mut R, crash: bool) -> Op {
         if crash && rng.gen_bool(1. / 30.) {
             return Op::DelayedCrash;
         }
+        if crash && rng.gen_bool(1. / 40.) {
+            let name = FAILPOINT_SITES[rng.gen_range(0, FAILPOINT_SITES.len())];
+            return Op::FailPoint(name.to_string(), rng.gen_range(0, 4));
+        }
         if rng.gen_bool(1. / 10.) {
             return Op::Restart;
         }
-        match rng.gen_range(0, 5) {
+        match rng.gen_range(0, 7) {
             0 => Op::Set,
             1 => Op::Del(rng.gen()),
             2 => Op::Id,
@@ -52,6 +137,29 @@ impl Op {
                 Op::Batched(ops)
             }
             4 => Op::Flush,
+            5 => {
+                // Pick a non-empty `lo < hi` window so `BTreeMap::range` never
+                // panics, then dress each endpoint as unbounded/included/excluded
+                // independently. `rng.gen()` chooses the scan direction.
+                fn bound<R: Rng>(rng: &mut R, value: u16) -> Bound<u16> {
+                    match rng.gen_range(0, 3) {
+                        0 => Bound::Unbounded,
+                        1 => Bound::Included(value),
+                        _ => Bound::Excluded(value),
+                    }
+                }
+                let lo = rng.gen_range(0u16, 256);
+                let hi = lo + rng.gen_range(1u16, 256);
+                Op::Range(bound(rng, lo), bound(rng, hi), rng.gen())
+            }
+            6 => {
+                // A conditional update on a deletable key. The value a present
+                // key holds always encodes the key itself, so a `Some`
+                // precondition/outcome is pinned to the key to keep the
+                // key<->value cross-check intact.
+                let (key, old, new) = cas_operands(rng);
+                Op::Cas { key, old, new }
+            }
             _ => unreachable!(),
         }
     }
@@ -68,6 +176,19 @@ impl Op {
                     match op {
                         BatchOp::Set => output.push(b's'),
                         BatchOp::Del(key) => output.append(&mut format!("d{}", key).into_bytes()),
+                        BatchOp::Cas { key, old, new } => {
+                            // `c<key>:<old>:<new>:`, each field self-delimited by a
+                            // trailing colon so the batch stream stays parseable.
+                            output.append(
+                                &mut format!(
+                                    "c{}:{}:{}:",
+                                    key,
+                                    cas_operand(old),
+                                    cas_operand(new)
+                                )
+                                .into_bytes(),
+                            );
+                        }
                     }
                 }
                 output
@@ -75,10 +196,30 @@ impl Op {
             Op::Restart => vec![b'-'],
             Op::Flush => vec![b'f'],
             Op::DelayedCrash => vec![b'_'],
+            Op::FailPoint(name, count) => format!("p{}\n{}", name, count).into_bytes(),
+            Op::Range(lo, hi, reverse) => {
+                // `R<dir><lo><hi>`, where dir is `f`/`r` and each bound is
+                // self-delimiting (`u`, or `i`/`e` followed by the decimal
+                // endpoint), so the two bounds can be parsed back in sequence.
+                let mut output = Vec::with_capacity(16);
+                output.push(b'R');
+                output.push(if *reverse { b'r' } else { b'f' });
+                encode_bound(&mut output, lo);
+                encode_bound(&mut output, hi);
+                output
+            }
+            Op::Cas { key, old, new } => {
+                // `C<key>:<old>:<new>`, mirroring the batch form but without the
+                // trailing delimiter since the op spans a whole line.
+                format!("C{}:{}:{}", key, cas_operand(old), cas_operand(new)).into_bytes()
+            }
             Op::CrashAndRecoveryVirtualOp(batch_counter) => {
                 format!("c{}", batch_counter).into_bytes()
             }
             Op::IdResultVirtualOp(id) => format!("I{}", id).into_bytes(),
+            Op::CasResultVirtualOp(succeeded) => {
+                vec![b'X', if *succeeded { b'1' } else { b'0' }]
+            }
         }
     }
 
@@ -121,6 +262,52 @@ impl Op {
             Ok(value)
         }
 
+        fn parse_u16(data: &[u8]) -> Result<u16, OpDecodeError> {
+            if data.len() == 0 {
+                return Err(OpDecodeError);
+            }
+            let mut value: u16 = 0;
+            for byte in data {
+                if *byte >= b'0' && *byte <= b'9' {
+                    value = value
+                        .checked_mul(10)
+                        .ok_or(OpDecodeError)?
+                        .checked_add((byte - b'0') as u16)
+                        .ok_or(OpDecodeError)?;
+                } else {
+                    return Err(OpDecodeError);
+                }
+            }
+            Ok(value)
+        }
+
+        // Parse one `Range` endpoint from the front of `data`, returning the
+        // decoded bound and how many bytes it consumed.
+        fn parse_bound(data: &[u8]) -> Result<(Bound<u16>, usize), OpDecodeError> {
+            if data.is_empty() {
+                return Err(OpDecodeError);
+            }
+            match data[0] {
+                b'u' => Ok((Bound::Unbounded, 1)),
+                b'i' | b'e' => {
+                    let end = data
+                        .iter()
+                        .skip(1)
+                        .position(|byte| *byte < b'0' || *byte > b'9')
+                        .map(|pos| pos + 1)
+                        .unwrap_or(data.len());
+                    let value = parse_u16(&data[1..end])?;
+                    let bound = if data[0] == b'i' {
+                        Bound::Included(value)
+                    } else {
+                        Bound::Excluded(value)
+                    };
+                    Ok((bound, end))
+                }
+                _ => Err(OpDecodeError),
+            }
+        }
+
         fn parse_u64(data: &[u8]) -> Result<u64, OpDecodeError> {
             if data.len() == 0 {
                 return Err(OpDecodeError);
@@ -140,6 +327,16 @@ impl Op {
             Ok(value)
         }
 
+        // A single compare-and-swap operand: `n` for `None`, else the decimal
+        // counter.
+        fn parse_cas_operand(field: &[u8]) -> Result<Option<u16>, OpDecodeError> {
+            if field == b"n" {
+                Ok(None)
+            } else {
+                Ok(Some(parse_u16(field)?))
+            }
+        }
+
         if data.len() == 0 {
             return Err(OpDecodeError);
         }
@@ -169,6 +366,30 @@ impl Op {
                             ops.push(BatchOp::Del(parse_u8(&data[1..number_end])?));
                             data = &data[number_end..];
                         }
+                        b'c' => {
+                            // `c<key>:<old>:<new>:` — three colon-terminated fields.
+                            let rest = &data[1..];
+                            let mut fields: Vec<&[u8]> = Vec::with_capacity(3);
+                            let mut start = 0;
+                            let mut consumed = None;
+                            for (i, byte) in rest.iter().enumerate() {
+                                if *byte == b':' {
+                                    fields.push(&rest[start..i]);
+                                    start = i + 1;
+                                    if fields.len() == 3 {
+                                        consumed = Some(i + 1);
+                                        break;
+                                    }
+                                }
+                            }
+                            let consumed = consumed.ok_or(OpDecodeError)?;
+                            ops.push(BatchOp::Cas {
+                                key: parse_u16(fields[0])?,
+                                old: parse_cas_operand(fields[1])?,
+                                new: parse_cas_operand(fields[2])?,
+                            });
+                            data = &data[1 + consumed..];
+                        }
                         _ => return Err(OpDecodeError),
                     }
                 }
@@ -177,8 +398,55 @@ impl Op {
             b'-' => Ok(Op::Restart),
             b'f' => Ok(Op::Flush),
             b'_' => Ok(Op::DelayedCrash),
+            b'p' => {
+                // Framing: `p<name>\n<count>`; the embedded newline separates the
+                // site name from its skip countdown.
+                let rest = &data[1..];
+                let newline = rest
+                    .iter()
+                    .position(|byte| *byte == b'\n')
+                    .ok_or(OpDecodeError)?;
+                let name = std::str::from_utf8(&rest[..newline])
+                    .map_err(|_| OpDecodeError)?
+                    .to_string();
+                Ok(Op::FailPoint(name, parse_u64(&rest[newline + 1..])?))
+            }
+            b'R' => {
+                let rest = &data[1..];
+                if rest.is_empty() {
+                    return Err(OpDecodeError);
+                }
+                let reverse = match rest[0] {
+                    b'f' => false,
+                    b'r' => true,
+                    _ => return Err(OpDecodeError),
+                };
+                let (lo, consumed) = parse_bound(&rest[1..])?;
+                let rest = &rest[1 + consumed..];
+                let (hi, consumed) = parse_bound(rest)?;
+                if consumed != rest.len() {
+                    return Err(OpDecodeError);
+                }
+                Ok(Op::Range(lo, hi, reverse))
+            }
+            b'C' => {
+                // `C<key>:<old>:<new>`, spanning the whole line (no terminator).
+                let mut parts = data[1..].split(|byte| *byte == b':');
+                let key = parse_u16(parts.next().ok_or(OpDecodeError)?)?;
+                let old = parse_cas_operand(parts.next().ok_or(OpDecodeError)?)?;
+                let new = parse_cas_operand(parts.next().ok_or(OpDecodeError)?)?;
+                if parts.next().is_some() {
+                    return Err(OpDecodeError);
+                }
+                Ok(Op::Cas { key, old, new })
+            }
             b'c' => Ok(Op::CrashAndRecoveryVirtualOp(parse_u32(&data[1..])?)),
             b'I' => Ok(Op::IdResultVirtualOp(parse_u64(&data[1..])?)),
+            b'X' => match &data[1..] {
+                b"1" => Ok(Op::CasResultVirtualOp(true)),
+                b"0" => Ok(Op::CasResultVirtualOp(false)),
+                _ => Err(OpDecodeError),
+            },
             _ => Err(OpDecodeError),
         }
     }
@@ -186,10 +454,13 @@ impl Op {
 
 impl BatchOp {
     fn generate<R: Rng>(rng: &mut R) -> BatchOp {
-        if rng.gen::<bool>() {
-            BatchOp::Set
-        } else {
-            BatchOp::Del(rng.gen::<u8>())
+        match rng.gen_range(0, 3) {
+            0 => BatchOp::Set,
+            1 => BatchOp::Del(rng.gen::<u8>()),
+            _ => {
+                let (key, old, new) = cas_operands(rng);
+                BatchOp::Cas { key, old, new }
+            }
         }
     }
 }
@@ -239,6 +510,16 @@ impl<R: Read> Iterator for OpReader<R> {
         if count == 0 || *self.buffer.last().unwrap() != b'\n' {
             return None;
         }
+        // A failpoint is framed across two lines (`p<name>\n<count>`); pull in the
+        // countdown line so `decode` sees the whole op.
+        if self.buffer.first() == Some(&b'p') {
+            if let Err(e) = self.reader.read_until(b'\n', &mut self.buffer) {
+                return Some(Err(e.into()));
+            }
+            if *self.buffer.last().unwrap() != b'\n' {
+                return None;
+            }
+        }
         match Op::decode(&self.buffer[..self.buffer.len() - 1]) {
             Ok(op) => Some(Ok(op)),
             Err(e) => Some(Err(e.into())),
@@ -246,23 +527,130 @@ impl<R: Read> Iterator for OpReader<R> {
     }
 }
 
+/// Default Gamma parameters for [`ValueSizer`]. The shape keeps the bulk of the
+/// mass small while the scale gives a heavy tail that regularly exceeds
+/// `SEGMENT_SIZE`, exercising the blob/overflow path.
+pub const DEFAULT_VALUE_SHAPE: f64 = 1.5;
+pub const DEFAULT_VALUE_SCALE: f64 = 80.0;
+
+/// Samples a value payload length from a Gamma distribution, so most values are
+/// tiny but a heavy tail crosses `SEGMENT_SIZE` boundaries. The length is no
+/// longer recoverable from the key, which is why [`value_factory`] writes it
+/// into the value itself.
+#[derive(Debug, Clone, Copy)]
+pub struct ValueSizer {
+    gamma: rand_distr::Gamma<f64>,
+}
+
+impl ValueSizer {
+    pub fn new(shape: f64, scale: f64) -> ValueSizer {
+        ValueSizer {
+            gamma: rand_distr::Gamma::new(shape, scale).unwrap(),
+        }
+    }
+
+    /// Sample a payload length, always at least the two counter bytes.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> usize {
+        use rand::distributions::Distribution;
+        std::cmp::max(self.gamma.sample(rng) as usize, 2)
+    }
+}
+
+impl Default for ValueSizer {
+    fn default() -> ValueSizer {
+        ValueSizer::new(DEFAULT_VALUE_SHAPE, DEFAULT_VALUE_SCALE)
+    }
+}
+
+/// Why a stored value failed to validate against the frame [`value_factory`]
+/// produces.
+#[derive(Debug)]
+pub enum CorruptionError {
+    /// The frame is too short for its own length prefix, or the length prefix
+    /// plus the trailing CRC overruns the bytes actually stored — the `u32`
+    /// length prefix itself is corrupted, or the value was truncated.
+    Truncated { len: usize, payload_len: usize },
+    /// The frame is the right length but its trailing CRC32 does not match its
+    /// payload — bit-rot inside a (possibly multi-segment) value that the
+    /// key↔prefix match alone would miss.
+    ChecksumMismatch { expected: u32, actual: u32 },
+}
+
+/// Recover the `set_counter` encoded at the front of a self-describing value
+/// produced by [`value_factory`]. The value is framed as a little-endian `u32`
+/// payload length, the payload (whose first two bytes are the big-endian counter
+/// — the same bytes as the key, for key↔value cross-checking), then a trailing
+/// little-endian `u32` CRC32 over the payload.
 pub fn decode_value(bytes: &[u8]) -> u16 {
-    if bytes[0] % 4 != 0 {
-        assert_eq!(bytes.len(), 2);
+    let payload_len = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+    assert_eq!(
+        bytes.len(),
+        4 + payload_len + 4,
+        "value length prefix disagrees with payload length"
+    );
+    (u16::from(bytes[4]) << 8) + u16::from(bytes[5])
+}
+
+/// Recompute the trailing CRC32 over the payload and compare it against the one
+/// stored in the value, returning the decoded `set_counter` on a match and a
+/// [`CorruptionError`] otherwise. This catches corruption of the payload interior
+/// that the leading counter bytes would not reveal, mirroring the
+/// checksum-on-every-block discipline of the thin-provisioning btree reader.
+/// The length prefix is bounds-checked before it is used to slice `bytes`: a
+/// truncated or malformed value is exactly the on-disk corruption this harness
+/// exists to surface, so it must come back as [`CorruptionError::Truncated`]
+/// rather than an unlabeled index-out-of-bounds panic.
+pub fn verify_value(bytes: &[u8]) -> Result<u16, CorruptionError> {
+    if bytes.len() < 4 {
+        return Err(CorruptionError::Truncated {
+            len: bytes.len(),
+            payload_len: 0,
+        });
+    }
+    let payload_len = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+    if bytes.len() < 4 + payload_len + 4 || payload_len < 2 {
+        return Err(CorruptionError::Truncated {
+            len: bytes.len(),
+            payload_len,
+        });
     }
-    (u16::from(bytes[0]) << 8) + u16::from(bytes[1])
+    let payload = &bytes[4..4 + payload_len];
+    let stored = u32::from_le_bytes([
+        bytes[4 + payload_len],
+        bytes[4 + payload_len + 1],
+        bytes[4 + payload_len + 2],
+        bytes[4 + payload_len + 3],
+    ]);
+    let actual = crc32fast::hash(payload);
+    if actual != stored {
+        return Err(CorruptionError::ChecksumMismatch {
+            expected: stored,
+            actual,
+        });
+    }
+    Ok((u16::from(payload[0]) << 8) + u16::from(payload[1]))
 }
 
-pub fn value_factory(set_counter: u16) -> Vec<u8> {
+/// Build a self-describing value of `length` payload bytes for `set_counter`: a
+/// little-endian `u32` length prefix, then the big-endian counter bytes followed
+/// by `lo`-filler out to `length`, then a little-endian `u32` CRC32 over the
+/// payload. The length prefix lets [`decode_value`] and [`verify_against_reference`]
+/// reconstruct and compare the expected payload even though the size was drawn
+/// from a distribution rather than derived from the key, and the trailing CRC
+/// lets [`verify_value`] detect interior bit-rot.
+pub fn value_factory(set_counter: u16, length: usize) -> Vec<u8> {
     let hi = (set_counter >> 8) as u8;
     let lo = set_counter as u8;
-    let mut val = vec![hi, lo];
-    if hi % 4 == 0 {
-        val.extend(vec![
-            lo;
-            hi as usize * SEGMENT_SIZE / 4 * set_counter as usize
-        ]);
-    }
+    let payload_len = std::cmp::max(length, 2);
+    let mut payload = Vec::with_capacity(payload_len);
+    payload.push(hi);
+    payload.push(lo);
+    payload.resize(payload_len, lo);
+    let crc = crc32fast::hash(&payload);
+    let mut val = Vec::with_capacity(4 + payload_len + 4);
+    val.extend_from_slice(&(payload_len as u32).to_le_bytes());
+    val.extend_from_slice(&payload);
+    val.extend_from_slice(&crc.to_le_bytes());
     val
 }
 
@@ -285,6 +673,13 @@ pub struct Reference {
     pub max_id: isize,
     pub crash_counter: u32,
     pub batch_counter: u32,
+    // The key and intended new value of the most recent [`Op::Cas`], awaiting its
+    // [`Op::CasResultVirtualOp`]. The speculative version pushed by
+    // `update_before` is corrected to the real tree outcome when the result
+    // arrives; if a crash lands first the speculative version stays among the
+    // key's versions, like any other unflushed write, so either outcome passes
+    // verification.
+    pending_cas: Option<(u16, Option<u16>)>,
 }
 
 // For each Set operation, one entry is inserted to the tree with a two-byte
@@ -304,7 +699,50 @@ impl Reference {
             max_id: -1,
             crash_counter: 0,
             batch_counter: 1,
+            pending_cas: None,
+        }
+    }
+
+    /// The model's current value for `key`: the most recent version's value, or
+    /// `None` when the key is absent. This is what a compare-and-swap
+    /// precondition is checked against.
+    fn current_value(&self, key: u16) -> Option<u16> {
+        self.map
+            .get(&key)
+            .and_then(|entry| entry.versions.last())
+            .and_then(|version| version.value)
+    }
+
+    /// Whether a transactional batch (one containing a [`BatchOp::Cas`]) would
+    /// commit. The batch commits only if every `Cas` precondition matches the
+    /// value the key holds at that point, with earlier ops in the same batch
+    /// visible — the read-your-writes view a sled transaction gives.
+    fn transaction_commits(&self, batch_ops: &[BatchOp]) -> bool {
+        use std::collections::HashMap;
+        let mut overlay: HashMap<u16, Option<u16>> = HashMap::new();
+        let mut set_counter = self.set_counter;
+        for op in batch_ops {
+            match op {
+                BatchOp::Set => {
+                    overlay.insert(set_counter, Some(set_counter));
+                    set_counter = set_counter.wrapping_add(1);
+                }
+                BatchOp::Del(key) => {
+                    overlay.insert(u16::from(*key), None);
+                }
+                BatchOp::Cas { key, old, new } => {
+                    let current = overlay
+                        .get(key)
+                        .copied()
+                        .unwrap_or_else(|| self.current_value(*key));
+                    if current != *old {
+                        return false;
+                    }
+                    overlay.insert(*key, *new);
+                }
+            }
         }
+        true
     }
 
     pub fn update_before(&mut self, op: &Op) {
@@ -343,6 +781,19 @@ impl Reference {
             }
             Op::Id => {}
             Op::Batched(batch_ops) => {
+                // A batch carrying a `Cas` is a transaction: it commits all-or-
+                // nothing, and only if every `Cas` precondition holds. Predict the
+                // outcome against the model and skip the whole batch on a predicted
+                // abort; a genuine crash-straddling disagreement is still caught by
+                // the batch-counter durability pruning, exactly as for a blind
+                // batch.
+                let transactional = batch_ops
+                    .iter()
+                    .any(|op| matches!(op, BatchOp::Cas { .. }));
+                if transactional && !self.transaction_commits(batch_ops) {
+                    return;
+                }
+
                 let crash_counter_copy = self.crash_counter;
                 let batch_counter_copy = self.batch_counter;
                 for batch_op in batch_ops {
@@ -373,12 +824,83 @@ impl Reference {
                                 v.crash_epoch = crash_counter_copy;
                             });
                         }
+                        BatchOp::Cas { key, new, .. } => {
+                            // The precondition already held (the batch committed),
+                            // so record the new value as a fresh version.
+                            let entry =
+                                self.map.entry(*key).or_insert_with(|| ReferenceEntry {
+                                    versions: vec![ReferenceVersion {
+                                        value: None,
+                                        batch: None,
+                                    }],
+                                    crash_epoch: crash_counter_copy,
+                                });
+                            entry.versions.push(ReferenceVersion {
+                                value: *new,
+                                batch: Some(batch_counter_copy),
+                            });
+                            entry.crash_epoch = crash_counter_copy;
+                        }
+                    }
+                }
+            }
+            Op::Cas { key, old, new } => {
+                // Record the model-predicted outcome as a new version: the `new`
+                // value when the current value matches `old`, otherwise a no-op
+                // version holding the unchanged value. The genuine result arrives
+                // in the following `CasResultVirtualOp`, which corrects this
+                // version when a crash made the prediction uncertain.
+                let crash_counter_copy = self.crash_counter;
+                let current = self.current_value(*key);
+                let resolved = if current == *old { *new } else { current };
+                let entry = self.map.entry(*key).or_insert_with(|| ReferenceEntry {
+                    versions: vec![ReferenceVersion {
+                        value: None,
+                        batch: None,
+                    }],
+                    crash_epoch: crash_counter_copy,
+                });
+                entry.versions.push(ReferenceVersion {
+                    value: resolved,
+                    batch: None,
+                });
+                entry.crash_epoch = crash_counter_copy;
+                self.pending_cas = Some((*key, *new));
+            }
+            Op::CasResultVirtualOp(succeeded) => {
+                // Reconcile the speculative version with what the tree actually
+                // did: keep `new` on a real success, fall back to the value the
+                // key held before the swap on a real failure.
+                if let Some((key, new)) = self.pending_cas.take() {
+                    if let Some(entry) = self.map.get_mut(&key) {
+                        let len = entry.versions.len();
+                        let prev = if len >= 2 {
+                            entry.versions[len - 2].value
+                        } else {
+                            None
+                        };
+                        if let Some(last) = entry.versions.last_mut() {
+                            last.value = if *succeeded { new } else { prev };
+                        }
                     }
                 }
             }
             Op::Restart => {}
             Op::Flush => {}
             Op::DelayedCrash => {}
+            Op::Range(_, _, _) => {}
+            Op::FailPoint(_, _) => {
+                // A fired failpoint tears the write path, so it acts as a crash
+                // boundary: discard every version written in the current crash
+                // epoch that a subsequent Flush has not yet made durable, then
+                // advance the crash counter like a recovery would.
+                for (_key, entry) in self.map.iter_mut() {
+                    if entry.versions.len() > 1 && entry.crash_epoch == self.crash_counter {
+                        entry.versions.truncate(1);
+                    }
+                }
+                self.crash_counter += 1;
+            }
             Op::CrashAndRecoveryVirtualOp(batch_counter) => {
                 self.crash_counter += 1;
                 prune_reference(&mut self.map, *batch_counter);
@@ -416,8 +938,12 @@ impl Reference {
                 }
             }
             Op::DelayedCrash => {}
+            Op::Range(_, _, _) => {}
+            Op::Cas { .. } => {}
+            Op::FailPoint(_, _) => {}
             Op::CrashAndRecoveryVirtualOp(_) => {}
             Op::IdResultVirtualOp(_) => {}
+            Op::CasResultVirtualOp(_) => {}
         }
     }
 }
@@ -468,18 +994,69 @@ pub fn verify_against_ops(tree: &sled::Tree, ops: &[Op]) -> Result<Reference, sl
     Ok(reference)
 }
 
-pub fn verify_against_reference(
+/// Like [`verify_against_ops`], but consumes the op history incrementally from an
+/// iterator (e.g. an [`OpLogReader`](common_utils::op_log::OpLogReader)) rather
+/// than requiring the whole `Vec<Op>` up front, so long compressed histories can
+/// be replayed without materializing every op in memory.
+pub fn verify_against_op_iter<I: IntoIterator<Item = io::Result<Op>>>(
     tree: &sled::Tree,
-    reference: &mut Reference,
-) -> Result<(), sled::Error> {
-    let mut ref_iter = reference.map.iter().map(|(ref rk, ref rv)| (**rk, *rv));
-    for res in tree.iter() {
-        let tree_key = &*res?.0;
-        if tree_key == BATCH_COUNTER_KEY {
-            continue;
-        }
-        let actual = decode_value(tree_key);
+    ops: I,
+) -> Result<Reference, sled::Error> {
+    let mut reference = Reference::new();
+    for res in ops {
+        let op = res.expect("failed to read op from log");
+        reference.update_before(&op);
+        reference.update_after(&op);
+    }
+    verify_against_reference(tree, &mut reference)?;
+    Ok(reference)
+}
 
+/// Cross-check a stored value against its key: the counter the value encodes
+/// must match the key, and its payload must be exactly what [`value_factory`]
+/// would produce for that counter and the stored length.
+fn check_value_against_key(actual: u16, tree_value: &[u8]) {
+    let stored_counter = verify_value(tree_value).unwrap_or_else(|err| match err {
+        CorruptionError::ChecksumMismatch { expected, actual: got } => panic!(
+            "value for key {} is corrupted: crc mismatch (expected {:#010x}, got {:#010x})",
+            actual, expected, got
+        ),
+        CorruptionError::Truncated { len, payload_len } => panic!(
+            "value for key {} is corrupted: frame is truncated (length prefix claims {} byte \
+            payload, but value is only {} bytes)",
+            actual, payload_len, len
+        ),
+    });
+    assert_eq!(
+        stored_counter, actual,
+        "value counter {} does not match key {}",
+        stored_counter, actual
+    );
+    let stored_len =
+        u32::from_le_bytes([tree_value[0], tree_value[1], tree_value[2], tree_value[3]]) as usize;
+    assert_eq!(
+        tree_value,
+        value_factory(stored_counter, stored_len).as_slice(),
+        "value payload for key {} is corrupted",
+        actual
+    );
+}
+
+/// The three-state verification at the heart of both the full-tree and range
+/// checks: `actual` is the sequence of keys the tree yielded (in the iteration
+/// order being checked) and `ref_iter` walks the reference entries in the same
+/// order. A key whose every version is absent must be skipped, a key whose every
+/// version is present must appear, and a key with a mix is uncertain — present or
+/// absent is both acceptable. `tree`/`map` are only used to build panic messages.
+fn check_key_sequence<'a, I>(
+    actual: &[u16],
+    mut ref_iter: I,
+    tree: &sled::Tree,
+    map: &BTreeMap<u16, ReferenceEntry>,
+) where
+    I: Iterator<Item = (u16, &'a ReferenceEntry)>,
+{
+    for &actual in actual {
         // make sure the tree value is in the reference
         while let Some((ref_key, ref_expected)) = ref_iter.next() {
             if ref_expected
@@ -500,7 +1077,7 @@ pub fn verify_against_reference(
                     actual, ref_key,
                     "expected to iterate over key {:?} but got {:?} instead due to it being \
                      missing in\n\ntree: {:?}\n\nreference: {:?}\n",
-                    ref_key, actual, tree, &reference.map
+                    ref_key, actual, tree, map
                 );
                 break;
             } else {
@@ -522,7 +1099,7 @@ pub fn verify_against_reference(
                     panic!(
                         "tree verification failed: expected {:?} got {:?}\
                          \n\ntree: {:?}\n\nreference: {:?}\n",
-                        ref_key, actual, tree, &reference.map
+                        ref_key, actual, tree, map
                     );
                 } else {
                     // we are iterating through the reference until we have an item that
@@ -534,7 +1111,7 @@ pub fn verify_against_reference(
         }
     }
 
-    while let Some((ref_key, ref_expected)) = ref_iter.next() {
+    for (ref_key, ref_expected) in ref_iter {
         if ref_expected
             .versions
             .iter()
@@ -548,13 +1125,277 @@ pub fn verify_against_reference(
             );
         }
     }
+}
+
+pub fn verify_against_reference(
+    tree: &sled::Tree,
+    reference: &mut Reference,
+) -> Result<(), sled::Error> {
+    let mut actual = Vec::new();
+    for res in tree.iter() {
+        let (tree_key, tree_value) = res?;
+        if &*tree_key == BATCH_COUNTER_KEY {
+            continue;
+        }
+        let key = (u16::from(tree_key[0]) << 8) + u16::from(tree_key[1]);
+        check_value_against_key(key, &tree_value);
+        actual.push(key);
+    }
+
+    let ref_iter = reference.map.iter().map(|(rk, rv)| (*rk, rv));
+    check_key_sequence(&actual, ref_iter, tree, &reference.map);
 
     Ok(())
 }
 
+/// Translate a `u16` key bound into the big-endian byte bound sled's
+/// `range` expects.
+fn key_bound(bound: Bound<u16>) -> Bound<[u8; 2]> {
+    match bound {
+        Bound::Unbounded => Bound::Unbounded,
+        Bound::Included(key) => Bound::Included(key.to_be_bytes()),
+        Bound::Excluded(key) => Bound::Excluded(key.to_be_bytes()),
+    }
+}
+
+/// Verify a bounded `range(lo..hi)` scan against the matching sub-map of the
+/// reference, both forward and (when `reverse` is set) reversed. The reversed
+/// scan must yield exactly the reverse of the forward one, which exercises
+/// sled's `DoubleEndedIterator` over bounded ranges — a path that has hidden
+/// bugs full scans miss.
+///
+/// The same `bounds` drive both the tree scan and the reference sub-map, so a
+/// `Bound::Excluded` endpoint drops the boundary key from both sides at once:
+/// an uncertain key sitting exactly on an excluded endpoint can never cause a
+/// false panic, because it is simply outside the window being compared.
+pub fn verify_range_against_reference<R: RangeBounds<u16>>(
+    tree: &sled::Tree,
+    reference: &Reference,
+    bounds: R,
+    reverse: bool,
+) -> Result<(), sled::Error> {
+    let lo = bounds.start_bound().cloned();
+    let hi = bounds.end_bound().cloned();
+    let byte_bounds = (key_bound(lo), key_bound(hi));
+
+    let mut actual = Vec::new();
+    for res in tree.range(byte_bounds) {
+        let (tree_key, tree_value) = res?;
+        if &*tree_key == BATCH_COUNTER_KEY {
+            continue;
+        }
+        let key = (u16::from(tree_key[0]) << 8) + u16::from(tree_key[1]);
+        check_value_against_key(key, &tree_value);
+        actual.push(key);
+    }
+
+    if reverse {
+        let mut reversed = Vec::new();
+        for res in tree.range(byte_bounds).rev() {
+            let (tree_key, _) = res?;
+            if &*tree_key == BATCH_COUNTER_KEY {
+                continue;
+            }
+            reversed.push((u16::from(tree_key[0]) << 8) + u16::from(tree_key[1]));
+        }
+        let mut expected: Vec<u16> = actual.clone();
+        expected.reverse();
+        assert_eq!(
+            reversed, expected,
+            "reversed range scan did not mirror the forward scan\n\ntree: {:?}\n",
+            tree
+        );
+    }
+
+    let ref_iter = reference.map.range((lo, hi)).map(|(rk, rv)| (*rk, rv));
+    check_key_sequence(&actual, ref_iter, tree, &reference.map);
+
+    Ok(())
+}
+
+/// Produce progressively smaller candidate histories for minimizing a failing
+/// `random_ops_workload` run, in the spirit of quickcheck's `Arbitrary::shrink`.
+/// Each yielded candidate is `history` with a single op removed, or — for
+/// `Op::Batched` — with a single inner `BatchOp` removed. Virtual ops
+/// (`CrashAndRecoveryVirtualOp`/`IdResultVirtualOp`/`CasResultVirtualOp`) are
+/// never removed on their own: an `Op::Id` is dropped together with the
+/// `IdResultVirtualOp` it generated, an `Op::Cas` together with its
+/// `CasResultVirtualOp`, and crash markers are left in place, so the
+/// reconstructed [`Reference`] stays consistent when the candidate is replayed.
+pub fn shrink(history: &[Op]) -> Box<dyn Iterator<Item = Vec<Op>>> {
+    let mut candidates = Vec::new();
+    for (i, op) in history.iter().enumerate() {
+        match op {
+            Op::CrashAndRecoveryVirtualOp(_)
+            | Op::IdResultVirtualOp(_)
+            | Op::CasResultVirtualOp(_) => {
+                // never drop a virtual op without its generating op
+            }
+            Op::Id => {
+                // drop the Id together with the IdResultVirtualOp it produced,
+                // so the id sequence in the reconstructed reference stays intact
+                let skip_to = match history.get(i + 1) {
+                    Some(Op::IdResultVirtualOp(_)) => i + 2,
+                    _ => i + 1,
+                };
+                let mut candidate = Vec::with_capacity(history.len());
+                candidate.extend_from_slice(&history[..i]);
+                candidate.extend_from_slice(&history[skip_to..]);
+                candidates.push(candidate);
+            }
+            Op::Cas { .. } => {
+                // drop the Cas together with the CasResultVirtualOp it produced
+                let skip_to = match history.get(i + 1) {
+                    Some(Op::CasResultVirtualOp(_)) => i + 2,
+                    _ => i + 1,
+                };
+                let mut candidate = Vec::with_capacity(history.len());
+                candidate.extend_from_slice(&history[..i]);
+                candidate.extend_from_slice(&history[skip_to..]);
+                candidates.push(candidate);
+            }
+            Op::Batched(batch_ops) => {
+                let mut candidate = Vec::with_capacity(history.len() - 1);
+                candidate.extend_from_slice(&history[..i]);
+                candidate.extend_from_slice(&history[i + 1..]);
+                candidates.push(candidate);
+                for j in 0..batch_ops.len() {
+                    let mut inner = batch_ops.clone();
+                    inner.remove(j);
+                    let mut candidate = history.to_vec();
+                    candidate[i] = Op::Batched(inner);
+                    candidates.push(candidate);
+                }
+            }
+            _ => {
+                let mut candidate = Vec::with_capacity(history.len() - 1);
+                candidate.extend_from_slice(&history[..i]);
+                candidate.extend_from_slice(&history[i + 1..]);
+                candidates.push(candidate);
+            }
+        }
+    }
+    Box::new(candidates.into_iter())
+}
+
+impl Arbitrary for BatchOp {
+    fn arbitrary(g: &mut Gen) -> BatchOp {
+        match u8::arbitrary(g) % 3 {
+            0 => BatchOp::Set,
+            1 => BatchOp::Del(u8::arbitrary(g)),
+            _ => {
+                // A present value always encodes its own key, so a `Some`
+                // precondition/outcome is pinned to the key.
+                let key = u16::from(u8::arbitrary(g));
+                let operand = |g: &mut Gen| if bool::arbitrary(g) { Some(key) } else { None };
+                BatchOp::Cas {
+                    key,
+                    old: operand(g),
+                    new: operand(g),
+                }
+            }
+        }
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = BatchOp>> {
+        match self {
+            BatchOp::Set => empty_shrinker(),
+            // Shrink the deleted key toward 0.
+            BatchOp::Del(key) => Box::new(key.shrink().map(BatchOp::Del)),
+            BatchOp::Cas { .. } => empty_shrinker(),
+        }
+    }
+}
+
+impl Arbitrary for Op {
+    /// Generate a concrete operation. The derived virtual ops
+    /// (`CrashAndRecoveryVirtualOp`, `IdResultVirtualOp`, `CasResultVirtualOp`)
+    /// are produced by replay, never by generation, so they are never emitted
+    /// here.
+    fn arbitrary(g: &mut Gen) -> Op {
+        match u8::arbitrary(g) % 8 {
+            0 => Op::Set,
+            1 => Op::Del(u8::arbitrary(g)),
+            2 => Op::Id,
+            3 => Op::Batched(Vec::arbitrary(g)),
+            4 => Op::Flush,
+            5 => {
+                fn bound(g: &mut Gen, value: u16) -> Bound<u16> {
+                    match u8::arbitrary(g) % 3 {
+                        0 => Bound::Unbounded,
+                        1 => Bound::Included(value),
+                        _ => Bound::Excluded(value),
+                    }
+                }
+                // Keep `lo < hi` so the range is always well-formed.
+                let lo = u16::arbitrary(g) % 256;
+                let hi = lo + 1 + u16::arbitrary(g) % 255;
+                Op::Range(bound(g, lo), bound(g, hi), bool::arbitrary(g))
+            }
+            6 => {
+                // A present value always encodes its own key, so a `Some`
+                // precondition/outcome is pinned to the key.
+                let key = u16::from(u8::arbitrary(g));
+                let operand = |g: &mut Gen| if bool::arbitrary(g) { Some(key) } else { None };
+                Op::Cas {
+                    key,
+                    old: operand(g),
+                    new: operand(g),
+                }
+            }
+            _ => Op::Restart,
+        }
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Op>> {
+        match self {
+            // Shrink the deleted key toward 0.
+            Op::Del(key) => Box::new(key.shrink().map(Op::Del)),
+            // `Vec::shrink` both halves the batch and shrinks its inner ops.
+            Op::Batched(ops) => Box::new(ops.shrink().map(Op::Batched)),
+            _ => empty_shrinker(),
+        }
+    }
+}
+
+/// A generated history of operations, wrapping `Vec<Op>` so quickcheck can drive
+/// property tests and minimize a failing sequence. Shrinking drops individual
+/// ops, then halves `Op::Batched` vectors and drops their inner `BatchOp`s, then
+/// shrinks `Del(k)` keys toward 0 — in that order, as `Vec`/`Op` shrinkers
+/// compose. No candidate ever contains a derived virtual op, so every shrunk
+/// `Vec<Op>` is accepted by [`construct_reference`] without panicking.
+#[derive(Clone, Debug)]
+pub struct OpSequence(pub Vec<Op>);
+
+impl Arbitrary for OpSequence {
+    fn arbitrary(g: &mut Gen) -> OpSequence {
+        OpSequence(Vec::arbitrary(g))
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = OpSequence>> {
+        let shrunk = self.0.shrink().filter_map(|ops| {
+            let has_virtual_op = ops.iter().any(|op| {
+                matches!(
+                    op,
+                    Op::CrashAndRecoveryVirtualOp(_)
+                        | Op::IdResultVirtualOp(_)
+                        | Op::CasResultVirtualOp(_)
+                )
+            });
+            if has_virtual_op {
+                None
+            } else {
+                Some(OpSequence(ops))
+            }
+        });
+        Box::new(shrunk)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{BatchOp, Op};
+    use super::{construct_reference, BatchOp, Op, OpSequence};
+    use quickcheck::Arbitrary;
 
     #[test]
     fn op_serialization_round_trip() {
@@ -580,6 +1421,37 @@ mod tests {
             Op::decode(&Op::encode(&Op::DelayedCrash)).unwrap(),
             Op::DelayedCrash
         );
+        assert_eq!(
+            Op::decode(&Op::encode(&Op::FailPoint("buffer write".into(), 0))).unwrap(),
+            Op::FailPoint("buffer write".into(), 0)
+        );
+        assert_eq!(
+            Op::decode(&Op::encode(&Op::FailPoint("snap write crc".into(), 42))).unwrap(),
+            Op::FailPoint("snap write crc".into(), 42)
+        );
+        use std::ops::Bound;
+        assert_eq!(
+            Op::decode(&Op::encode(&Op::Range(Bound::Unbounded, Bound::Unbounded, false))).unwrap(),
+            Op::Range(Bound::Unbounded, Bound::Unbounded, false)
+        );
+        assert_eq!(
+            Op::decode(&Op::encode(&Op::Range(
+                Bound::Included(5),
+                Bound::Excluded(300),
+                true
+            )))
+            .unwrap(),
+            Op::Range(Bound::Included(5), Bound::Excluded(300), true)
+        );
+        assert_eq!(
+            Op::decode(&Op::encode(&Op::Range(
+                Bound::Excluded(0),
+                Bound::Included(65535),
+                false
+            )))
+            .unwrap(),
+            Op::Range(Bound::Excluded(0), Bound::Included(65535), false)
+        );
         assert_eq!(
             Op::decode(&Op::encode(&Op::CrashAndRecoveryVirtualOp(1))).unwrap(),
             Op::CrashAndRecoveryVirtualOp(1)
@@ -596,5 +1468,136 @@ mod tests {
             Op::decode(&Op::encode(&Op::IdResultVirtualOp(123456))).unwrap(),
             Op::IdResultVirtualOp(123456)
         );
+        for cas in [
+            Op::Cas {
+                key: 7,
+                old: None,
+                new: Some(7),
+            },
+            Op::Cas {
+                key: 200,
+                old: Some(200),
+                new: None,
+            },
+            Op::Cas {
+                key: 0,
+                old: None,
+                new: None,
+            },
+        ] {
+            assert_eq!(Op::decode(&Op::encode(&cas)).unwrap(), cas);
+        }
+        assert_eq!(
+            Op::decode(&Op::encode(&Op::CasResultVirtualOp(true))).unwrap(),
+            Op::CasResultVirtualOp(true)
+        );
+        assert_eq!(
+            Op::decode(&Op::encode(&Op::CasResultVirtualOp(false))).unwrap(),
+            Op::CasResultVirtualOp(false)
+        );
+        let txn = Op::Batched(vec![
+            BatchOp::Set,
+            BatchOp::Cas {
+                key: 3,
+                old: Some(3),
+                new: None,
+            },
+            BatchOp::Del(9),
+            BatchOp::Cas {
+                key: 42,
+                old: None,
+                new: Some(42),
+            },
+        ]);
+        assert_eq!(Op::decode(&Op::encode(&txn)).unwrap(), txn);
+    }
+
+    #[test]
+    fn value_crc_round_trip_and_corruption() {
+        for (counter, length) in [(0u16, 2usize), (300, 2), (1, 600), (65535, 300)] {
+            let value = super::value_factory(counter, length);
+            assert_eq!(super::decode_value(&value), counter);
+            assert_eq!(super::verify_value(&value).unwrap(), counter);
+
+            // Flip a byte in the payload interior: the CRC must now disagree.
+            let mut corrupt = value.clone();
+            let victim = corrupt.len() - 5;
+            corrupt[victim] ^= 0xff;
+            assert!(matches!(
+                super::verify_value(&corrupt),
+                Err(super::CorruptionError::ChecksumMismatch { .. })
+            ));
+
+            // Truncate the value: a short read must be reported as corruption,
+            // not panic via an unchecked slice index.
+            let truncated = &value[..value.len() - 1];
+            assert!(matches!(
+                super::verify_value(truncated),
+                Err(super::CorruptionError::Truncated { .. })
+            ));
+        }
+
+        // A garbled length prefix that claims a payload far larger than the
+        // bytes actually present must also be reported as corruption.
+        let mut garbled_len = super::value_factory(0, 2);
+        garbled_len[0..4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            super::verify_value(&garbled_len),
+            Err(super::CorruptionError::Truncated { .. })
+        ));
+
+        // A frame too short to even hold the length prefix must not panic.
+        assert!(matches!(
+            super::verify_value(&[0u8, 1, 2]),
+            Err(super::CorruptionError::Truncated { .. })
+        ));
+    }
+
+    #[test]
+    fn shrink_drops_id_with_its_result() {
+        let history = vec![
+            Op::CrashAndRecoveryVirtualOp(0),
+            Op::Id,
+            Op::IdResultVirtualOp(5),
+            Op::Set,
+        ];
+        let candidates: Vec<_> = super::shrink(&history).collect();
+        // crash markers and lone id-results are never removed on their own
+        assert!(candidates
+            .iter()
+            .all(|c| c.contains(&Op::CrashAndRecoveryVirtualOp(0))));
+        // no candidate ever contains an IdResultVirtualOp without a preceding Id
+        for candidate in &candidates {
+            for (i, op) in candidate.iter().enumerate() {
+                if let Op::IdResultVirtualOp(_) = op {
+                    assert!(i > 0 && candidate[i - 1] == Op::Id);
+                }
+            }
+        }
+        // dropping the Id takes its paired result with it
+        assert!(candidates.contains(&vec![Op::CrashAndRecoveryVirtualOp(0), Op::Set]));
+        // every candidate is strictly shorter than the input
+        assert!(candidates.iter().all(|c| c.len() < history.len()));
+    }
+
+    #[test]
+    fn op_sequence_shrink_is_replayable() {
+        let sequence = OpSequence(vec![
+            Op::Set,
+            Op::Batched(vec![BatchOp::Set, BatchOp::Del(200)]),
+            Op::Del(255),
+            Op::Flush,
+        ]);
+        for candidate in sequence.shrink() {
+            // shrinking never fabricates the derived virtual ops
+            for op in &candidate.0 {
+                assert!(!matches!(
+                    op,
+                    Op::CrashAndRecoveryVirtualOp(_) | Op::IdResultVirtualOp(_)
+                ));
+            }
+            // and every candidate reconstructs a reference without panicking
+            let _ = construct_reference(&candidate.0);
+        }
     }
 }
@@ -0,0 +1,44 @@
+use std::convert::TryInto;
+
+use common_utils::*;
+
+const NUM_ACCOUNTS: u16 = 8;
+const INITIAL_BALANCE: u64 = 100;
+const TOTAL: u64 = NUM_ACCOUNTS as u64 * INITIAL_BALANCE;
+const CACHE_CAPACITY: u64 = 1024 * 1024;
+const SEGMENT_SIZE: usize = 256;
+
+fn decode_balance(bytes: &[u8]) -> u64 {
+    u64::from_be_bytes(bytes.try_into().unwrap())
+}
+
+fn main() -> Result<(), sled::Error> {
+    let (crashed_state_directory, _stdout_file) = checker_arguments();
+    let db = config(crashed_state_directory, CACHE_CAPACITY, SEGMENT_SIZE, true).open()?;
+
+    let mut sum = 0u64;
+    let mut count = 0u16;
+    for res in db.iter() {
+        let (_key, value) = res?;
+        sum += decode_balance(&value);
+        count += 1;
+    }
+
+    // The workload seeds all accounts in a single transaction, so recovery
+    // either sees the full ledger or none of it. If the ledger is present, the
+    // atomic transfers must have conserved the total.
+    if count > 0 {
+        assert_eq!(
+            count, NUM_ACCOUNTS,
+            "expected {} accounts after recovery, found {}",
+            NUM_ACCOUNTS, count,
+        );
+        assert_eq!(
+            sum, TOTAL,
+            "token conservation violated after recovery: balances sum to {} but should be {}",
+            sum, TOTAL,
+        );
+    }
+
+    Ok(())
+}
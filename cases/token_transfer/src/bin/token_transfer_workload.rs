@@ -0,0 +1,118 @@
+use std::{convert::TryInto, process, sync::Arc, thread};
+
+use rand::Rng;
+
+use common_utils::*;
+
+const DEFAULT_THREADS: usize = 4;
+const TRANSFERS_PER_THREAD: usize = 2000;
+const NUM_ACCOUNTS: u16 = 8;
+const INITIAL_BALANCE: u64 = 100;
+const TOTAL: u64 = NUM_ACCOUNTS as u64 * INITIAL_BALANCE;
+const CACHE_CAPACITY: u64 = 1024 * 1024;
+const SEGMENT_SIZE: usize = 256;
+
+// This workload spawns several threads that each move a single unit of a
+// "token" balance between two randomly chosen accounts inside a single sled
+// transaction. Because each transfer is atomic, the sum of all account
+// balances is invariant no matter when a SIGKILL lands. The checker reopens the
+// recovered tree and verifies that the balances still sum to `TOTAL`, so a
+// partially applied transfer (a lost or double-counted token) is detected.
+
+fn account_key(account: u16) -> Vec<u8> {
+    account.to_be_bytes().to_vec()
+}
+
+fn encode_balance(balance: u64) -> Vec<u8> {
+    balance.to_be_bytes().to_vec()
+}
+
+fn decode_balance(bytes: &[u8]) -> u64 {
+    u64::from_be_bytes(bytes.try_into().unwrap())
+}
+
+fn run(threads: usize, crash: bool) -> Result<(), sled::Error> {
+    block_on_database_lock(WORKLOAD_DIR)?;
+    let db = Arc::new(config(WORKLOAD_DIR, CACHE_CAPACITY, SEGMENT_SIZE, true).open()?);
+
+    // Seed any accounts that a previous crash never persisted. The whole seeding
+    // step is one transaction, so recovery never sees a half-seeded ledger.
+    db.transaction::<_, _, ()>(|tree| {
+        for account in 0..NUM_ACCOUNTS {
+            if tree.get(account_key(account))?.is_none() {
+                tree.insert(account_key(account), encode_balance(INITIAL_BALANCE))?;
+            }
+        }
+        Ok(())
+    })
+    .unwrap();
+    db.flush()?;
+
+    if crash {
+        start_sigkill_timer();
+    }
+
+    let mut handles = Vec::with_capacity(threads);
+    for _ in 0..threads {
+        let db = db.clone();
+        handles.push(thread::spawn(move || {
+            let mut rng = rand::thread_rng();
+            for _ in 0..TRANSFERS_PER_THREAD {
+                let from = rng.gen_range(0, NUM_ACCOUNTS);
+                let mut to = rng.gen_range(0, NUM_ACCOUNTS);
+                while to == from {
+                    to = rng.gen_range(0, NUM_ACCOUNTS);
+                }
+                db.transaction::<_, _, ()>(|tree| {
+                    let from_balance = decode_balance(&tree.get(account_key(from))?.unwrap());
+                    let to_balance = decode_balance(&tree.get(account_key(to))?.unwrap());
+                    if from_balance > 0 {
+                        tree.insert(account_key(from), encode_balance(from_balance - 1))?;
+                        tree.insert(account_key(to), encode_balance(to_balance + 1))?;
+                    }
+                    Ok(())
+                })
+                .unwrap();
+            }
+        }));
+    }
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    db.flush()?;
+    Ok(())
+}
+
+fn main() {
+    raise_fd_limit();
+    let matches = App::new("token_transfer_workload")
+        .version(crate_version!())
+        .arg(
+            Arg::with_name("threads")
+                .long("threads")
+                .short("j")
+                .required(false)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("crash")
+                .long("crash")
+                .short("c")
+                .takes_value(false),
+        )
+        .get_matches();
+    let threads = if let Some(threads) = matches.value_of("threads") {
+        if let Ok(threads) = threads.parse() {
+            threads
+        } else {
+            eprintln!("{}", matches.usage());
+            process::exit(1);
+        }
+    } else {
+        DEFAULT_THREADS
+    };
+    let crash = matches.is_present("crash");
+
+    crash_recovery_loop(run, threads, crash);
+}
@@ -5,6 +5,7 @@ use std::{
     io::{self, BufRead, BufReader, Write},
     ops::Not,
     process::{Command, Stdio},
+    sync::OnceLock,
 };
 
 use serde_json::Deserializer;
@@ -17,6 +18,69 @@ pub enum Satisfiability {
     Unsatisfiable,
 }
 
+/// The consistency guarantee a recorded history is checked against. Each level
+/// selects which edge classes are added to the serialization graph and which
+/// graph must be acyclic.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum IsolationLevel {
+    /// Acyclic dependency graph over ww/wr/rw edges; real-time order is ignored.
+    Serializable,
+    /// Serializable plus real-time precedence edges.
+    StrictSerializable,
+    /// Reads observe a consistent snapshot as of the transaction's start and
+    /// two committed writers of a key may not overlap in time; checked against
+    /// commit timestamps rather than through the serialization graph (see
+    /// [`check_snapshot_isolation`]).
+    SnapshotIsolation,
+    /// Forbids cycles over ww/wr edges only (G1c); anti-dependencies are ignored.
+    ReadCommitted,
+}
+
+impl IsolationLevel {
+    /// Whether real-time precedence edges (`A.end < B.start ⇒ A → B`) are part of
+    /// the graph for this level.
+    pub fn uses_real_time(self) -> bool {
+        matches!(self, IsolationLevel::StrictSerializable)
+    }
+
+    /// Whether read-write anti-dependency edges are added to the graph for this
+    /// level. Read Committed ignores them entirely. Snapshot Isolation adds them
+    /// when its graph is built (for the DOT export), but its acceptance check
+    /// does not use the graph: it works from commit timestamps (see
+    /// [`check_snapshot_isolation`]).
+    pub fn uses_anti_dependencies(self) -> bool {
+        matches!(
+            self,
+            IsolationLevel::Serializable
+                | IsolationLevel::StrictSerializable
+                | IsolationLevel::SnapshotIsolation
+        )
+    }
+
+    /// Parse a level from its [`tag`](Self::tag) string, as accepted in the
+    /// `SLED_ISOLATION_LEVEL` environment variable.
+    pub fn from_tag(tag: &str) -> Option<IsolationLevel> {
+        match tag {
+            "serializable" => Some(IsolationLevel::Serializable),
+            "strict-serializable" => Some(IsolationLevel::StrictSerializable),
+            "snapshot-isolation" => Some(IsolationLevel::SnapshotIsolation),
+            "read-committed" => Some(IsolationLevel::ReadCommitted),
+            _ => None,
+        }
+    }
+
+    /// A short tag embedded in the generated DIMACS comments so a single recorded
+    /// history checked at multiple levels produces self-describing output.
+    pub fn tag(self) -> &'static str {
+        match self {
+            IsolationLevel::Serializable => "serializable",
+            IsolationLevel::StrictSerializable => "strict-serializable",
+            IsolationLevel::SnapshotIsolation => "snapshot-isolation",
+            IsolationLevel::ReadCommitted => "read-committed",
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum MonosatError {
     Io(io::Error),
@@ -38,8 +102,17 @@ impl From<io::Error> for MonosatError {
     }
 }
 
-pub fn run_monosat(dimacs: &str) -> Result<Satisfiability, MonosatError> {
+/// A satisfying assignment recovered from MonoSAT's witness (`v …`) output,
+/// mapping each variable to the boolean value the solver chose. Lets downstream
+/// code recover which edge/version-order variables were selected.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct Model {
+    assignment: BTreeMap<Variable, bool>,
+}
+
+pub fn run_monosat(dimacs: &str) -> Result<(Satisfiability, Model), MonosatError> {
     let mut child = Command::new("monosat")
+        .arg("-witness")
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .spawn()?;
@@ -47,12 +120,186 @@ pub fn run_monosat(dimacs: &str) -> Result<Satisfiability, MonosatError> {
     stdin.write_all(dimacs.as_bytes())?;
     stdin.write_all(b"\n")?;
     let stdout = child.wait_with_output()?.stdout;
-    match &stdout {
-        output if output == b"s SATISFIABLE\n" => Ok(Satisfiability::Satisfiable),
-        output if output == b"s UNSATISFIABLE\n" => Ok(Satisfiability::Unsatisfiable),
-        _ => Err(MonosatError::OutputParseError),
+    let text = String::from_utf8_lossy(&stdout);
+
+    // Parse line-by-line so the solver's banner/comment lines and the witness
+    // assignment are tolerated, and the `s` status line may appear anywhere.
+    let mut satisfiability = None;
+    let mut model = Model::default();
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix("s ") {
+            satisfiability = match rest.trim() {
+                "SATISFIABLE" => Some(Satisfiability::Satisfiable),
+                "UNSATISFIABLE" => Some(Satisfiability::Unsatisfiable),
+                _ => return Err(MonosatError::OutputParseError),
+            };
+        } else if let Some(rest) = line.strip_prefix("v ") {
+            // A `v` line lists signed variable numbers; 0 terminates the model.
+            for token in rest.split_whitespace() {
+                let literal: i64 = token.parse().map_err(|_| MonosatError::OutputParseError)?;
+                if literal == 0 {
+                    break;
+                }
+                let variable = Variable(literal.unsigned_abs() as usize);
+                model.assignment.insert(variable, literal > 0);
+            }
+        }
+    }
+
+    match satisfiability {
+        Some(satisfiability) => Ok((satisfiability, model)),
+        None => Err(MonosatError::OutputParseError),
     }
 }
+/// An error from a [`SatBackend`].
+#[derive(Debug)]
+enum BackendError {
+    Monosat(MonosatError),
+    Cnf(String),
+}
+
+impl fmt::Display for BackendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackendError::Monosat(e) => e.fmt(f),
+            BackendError::Cnf(e) => write!(f, "in-process CNF solver error: {}", e),
+        }
+    }
+}
+
+impl From<MonosatError> for BackendError {
+    fn from(e: MonosatError) -> BackendError {
+        BackendError::Monosat(e)
+    }
+}
+
+/// A pluggable satisfiability backend. The graph-acyclicity constraint can be
+/// discharged either by the external MonoSAT binary or by an in-process CNF
+/// solver, so the crate works whether or not MonoSAT is installed.
+trait SatBackend {
+    fn solve(&self, gnf: &Gnf) -> Result<Satisfiability, BackendError>;
+}
+
+/// Backend that shells out to the external `monosat` binary.
+struct MonosatBackend;
+
+impl SatBackend for MonosatBackend {
+    fn solve(&self, gnf: &Gnf) -> Result<Satisfiability, BackendError> {
+        Ok(run_monosat(&gnf.to_dimacs())?.0)
+    }
+}
+
+/// Backend that solves entirely in process using a plain-CNF solver, encoding
+/// acyclicity directly rather than relying on MonoSAT's graph theory: a total
+/// order `o_ij` is introduced over every ordered pair of transaction nodes, with
+/// clauses enforcing totality, antisymmetry and transitivity, and each selected
+/// dependency edge `i→j` forces `o_ij`. A satisfying assignment is then a valid
+/// serialization order; unsatisfiability means a cycle.
+struct CnfBackend;
+
+impl CnfBackend {
+    /// Build the pure-CNF encoding as DIMACS clauses (vectors of signed variable
+    /// numbers).
+    fn encode(gnf: &Gnf) -> Vec<Vec<i32>> {
+        let mut clauses: Vec<Vec<i32>> = Vec::new();
+
+        // Preserve the existing meta-clauses (dependency-edge selection logic),
+        // skipping the acyclicity unit clause, which is replaced by the order
+        // encoding below.
+        for ClausesWithComment { clauses: cs, .. } in &gnf.meta_clauses {
+            for clause in cs {
+                if clause.literals.len() == 1 {
+                    if let Literal::Variable(v) = clause.literals[0] {
+                        if v == gnf.acyclic_variable() {
+                            continue;
+                        }
+                    }
+                }
+                clauses.push(clause.literals.iter().map(literal_to_dimacs).collect());
+            }
+        }
+
+        // Allocate an order variable o_ij for each ordered pair of nodes.
+        let n = gnf.n_nodes;
+        let mut next_var = (gnf.n_variables + 1) as i32;
+        let mut order = std::collections::HashMap::new();
+        for i in 0..n {
+            for j in 0..n {
+                if i != j {
+                    order.insert((i, j), next_var);
+                    next_var += 1;
+                }
+            }
+        }
+        let o = |i: usize, j: usize| *order.get(&(i, j)).unwrap();
+
+        // Totality and antisymmetry for every unordered pair.
+        for i in 0..n {
+            for j in (i + 1)..n {
+                clauses.push(vec![o(i, j), o(j, i)]);
+                clauses.push(vec![-o(i, j), -o(j, i)]);
+            }
+        }
+        // Transitivity: o_ij ∧ o_jk → o_ik.
+        for i in 0..n {
+            for j in 0..n {
+                for k in 0..n {
+                    if i != j && j != k && i != k {
+                        clauses.push(vec![-o(i, j), -o(j, k), o(i, k)]);
+                    }
+                }
+            }
+        }
+        // Each selected edge i→j forces the order o_ij.
+        for (edge, _) in &gnf.edges {
+            clauses.push(vec![-(edge.variable.0 as i32), o(edge.from.0, edge.to.0)]);
+        }
+
+        clauses
+    }
+}
+
+impl SatBackend for CnfBackend {
+    fn solve(&self, gnf: &Gnf) -> Result<Satisfiability, BackendError> {
+        let clauses = CnfBackend::encode(gnf);
+        match splr::Certificate::try_from(clauses) {
+            Ok(splr::Certificate::SAT(_)) => Ok(Satisfiability::Satisfiable),
+            Ok(splr::Certificate::UNSAT) => Ok(Satisfiability::Unsatisfiable),
+            Err(e) => Err(BackendError::Cnf(format!("{:?}", e))),
+        }
+    }
+}
+
+static BACKEND: OnceLock<Box<dyn SatBackend + Sync>> = OnceLock::new();
+
+/// The [`SatBackend`] used for every solve in this process: MonoSAT when its
+/// binary is on `PATH`, otherwise the slower in-process CNF encoding, so the
+/// checker works whether or not MonoSAT is installed. Selected once and
+/// cached, so a missing binary costs a single probe rather than a subprocess
+/// spawn on every call.
+fn backend() -> &'static (dyn SatBackend + Sync) {
+    BACKEND
+        .get_or_init(|| match run_monosat("") {
+            Ok((Satisfiability::Satisfiable, _)) => Box::new(MonosatBackend),
+            _ => {
+                eprintln!(
+                    "monosat binary not found on PATH (or failed to run); falling back \
+                    to the in-process CNF backend"
+                );
+                Box::new(CnfBackend)
+            }
+        })
+        .as_ref()
+}
+
+fn literal_to_dimacs(literal: &Literal) -> i32 {
+    match literal {
+        Literal::Variable(Variable(v)) => *v as i32,
+        Literal::Negation(Variable(v)) => -(*v as i32),
+    }
+}
+
 #[derive(Debug)]
 struct TransactionCrashed {
     start: u128,
@@ -322,6 +569,97 @@ impl Expression {
             })
             .collect()
     }
+
+    /// The number of nodes (internal and leaf) in the expression tree. Used to
+    /// decide when the distributive `to_cnf` is likely to blow up and the Tseitin
+    /// encoding should be used instead.
+    fn node_count(&self) -> usize {
+        match self {
+            Expression::Conjunction(exprs) | Expression::Disjunction(exprs) => {
+                1 + exprs.iter().map(Expression::node_count).sum::<usize>()
+            }
+            Expression::Literal(_) => 1,
+        }
+    }
+
+    /// Encode the expression into an *equisatisfiable* CNF of linear size by
+    /// introducing a fresh definition variable (via `Gnf::add_variable`) for each
+    /// internal node, rather than distributing ORs over ANDs (which is worst-case
+    /// exponential). The returned clauses assert that the top-level expression is
+    /// true.
+    ///
+    /// When `polarity_aware` is set, the Plaisted–Greenbaum optimization is used:
+    /// because the whole expression is asserted positively and no compound
+    /// subexpression appears under a negation, only the implication in the needed
+    /// direction is emitted for each definition, roughly halving the clause count.
+    fn to_cnf_tseitin(self, gnf: &mut Gnf, polarity_aware: bool) -> Vec<Clause> {
+        let mut clauses = Vec::new();
+        let top = self.define(gnf, polarity_aware, &mut clauses);
+        clauses.push(Clause {
+            literals: vec![top],
+        });
+        clauses
+    }
+
+    /// Recursively emit the definition clauses for this node and return the
+    /// literal standing for it.
+    fn define(self, gnf: &mut Gnf, polarity_aware: bool, clauses: &mut Vec<Clause>) -> Literal {
+        match self {
+            Expression::Literal(literal) => literal,
+            Expression::Disjunction(exprs) => {
+                let child_literals: Vec<Literal> = exprs
+                    .into_iter()
+                    .map(|e| e.define(gnf, polarity_aware, clauses))
+                    .collect();
+                let y = gnf.add_variable();
+                // y -> (a ∨ b ∨ …): (¬y ∨ a ∨ b ∨ …)
+                let mut forward = vec![Literal::Negation(y)];
+                forward.extend(child_literals.iter().copied());
+                clauses.push(Clause { literals: forward });
+                if !polarity_aware {
+                    // (a ∨ b ∨ …) -> y: one binary clause (y ∨ ¬aᵢ) per child
+                    for lit in &child_literals {
+                        clauses.push(Clause {
+                            literals: vec![Literal::Variable(y), negate(*lit)],
+                        });
+                    }
+                }
+                Literal::Variable(y)
+            }
+            Expression::Conjunction(exprs) => {
+                let child_literals: Vec<Literal> = exprs
+                    .into_iter()
+                    .map(|e| e.define(gnf, polarity_aware, clauses))
+                    .collect();
+                let y = gnf.add_variable();
+                // y -> (a ∧ b ∧ …): (¬y ∨ aᵢ) per child
+                for lit in &child_literals {
+                    clauses.push(Clause {
+                        literals: vec![Literal::Negation(y), *lit],
+                    });
+                }
+                if !polarity_aware {
+                    // (a ∧ b ∧ …) -> y: (y ∨ ¬a ∨ ¬b ∨ …)
+                    let mut backward = vec![Literal::Variable(y)];
+                    backward.extend(child_literals.iter().copied().map(negate));
+                    clauses.push(Clause { literals: backward });
+                }
+                Literal::Variable(y)
+            }
+        }
+    }
+}
+
+/// The node-count above which `to_cnf_tseitin` is preferred over the distributive
+/// `to_cnf`.
+const TSEITIN_THRESHOLD: usize = 32;
+
+/// Negate a literal.
+fn negate(literal: Literal) -> Literal {
+    match literal {
+        Literal::Variable(v) => Literal::Negation(v),
+        Literal::Negation(v) => Literal::Variable(v),
+    }
 }
 
 #[cfg(test)]
@@ -472,6 +810,108 @@ impl Gnf {
         self.acyclic_variable
     }
 
+    /// The set of variables forced true by a positive unit clause. These are the
+    /// real-time and unconditional dependency edges that appear in every model.
+    fn forced_variables(&self) -> BTreeSet<Variable> {
+        let mut forced = BTreeSet::new();
+        for ClausesWithComment { clauses, .. } in &self.meta_clauses {
+            for clause in clauses {
+                if let [Literal::Variable(v)] = clause.literals[..] {
+                    forced.insert(v);
+                }
+            }
+        }
+        forced
+    }
+
+    /// When the solver reports the graph has no acyclic orientation, recover a
+    /// concrete anomaly: build the directed graph of forced (and, if supplied, the
+    /// solver-fixed version-order) edges, find a strongly connected component with
+    /// Tarjan's algorithm, extract a shortest cycle within it, and classify the
+    /// cycle by the edge kinds along it.
+    ///
+    /// `model` optionally maps edge variables to the boolean values the solver
+    /// chose, letting version-order (candidate W-W/R-W) edges that were fixed true
+    /// participate in the recovered cycle.
+    fn extract_anomaly(&self, model: Option<&BTreeMap<Variable, bool>>) -> Option<Anomaly> {
+        let forced = self.forced_variables();
+        // adjacency: node -> Vec<(node, EdgeKind)>
+        let mut adjacency: Vec<Vec<(usize, EdgeKind)>> = vec![Vec::new(); self.n_nodes];
+        for (edge, comment) in &self.edges {
+            let active = forced.contains(&edge.variable)
+                || model.map_or(false, |m| m.get(&edge.variable).copied().unwrap_or(false));
+            if active {
+                adjacency[edge.from.0].push((edge.to.0, EdgeKind::from_comment(comment)));
+            }
+        }
+
+        let scc = tarjan_scc(&adjacency);
+        for component in scc {
+            if component.len() < 2 && !has_self_loop(&adjacency, &component) {
+                continue;
+            }
+            let component_set: BTreeSet<usize> = component.iter().copied().collect();
+            if let Some(cycle) = shortest_cycle(&adjacency, &component_set) {
+                let class = classify_cycle(&cycle);
+                return Some(Anomaly { cycle, class });
+            }
+        }
+        None
+    }
+
+    /// Fast pre-pass run before the SAT solver: build the graph of only the
+    /// edges forced true in every model — the unconditional W-R dependencies,
+    /// the forced single-write R-W anti-dependencies and, at the levels that add
+    /// them, the real-time and predicate R-W edges — and look for a cycle with
+    /// Tarjan's algorithm. Any such cycle is a dependency cycle regardless of how
+    /// the candidate W-W/R-W variables are assigned, so the history fails the
+    /// level being checked without invoking MonoSAT. Returns a structured witness
+    /// reusing the descriptive strings the edges were added with.
+    fn mandatory_cycle(&self) -> Option<AnomalyWitness> {
+        let forced = self.forced_variables();
+        let mut adjacency: Vec<Vec<(usize, EdgeKind)>> = vec![Vec::new(); self.n_nodes];
+        let mut descriptions: BTreeMap<(usize, usize, EdgeKind), String> = BTreeMap::new();
+        for (edge, comment) in &self.edges {
+            if forced.contains(&edge.variable) {
+                let kind = EdgeKind::from_comment(comment);
+                adjacency[edge.from.0].push((edge.to.0, kind));
+                descriptions
+                    .entry((edge.from.0, edge.to.0, kind))
+                    .or_insert_with(|| comment.clone());
+            }
+        }
+
+        for component in tarjan_scc(&adjacency) {
+            if component.len() < 2 && !has_self_loop(&adjacency, &component) {
+                continue;
+            }
+            let component_set: BTreeSet<usize> = component.iter().copied().collect();
+            if let Some(cycle) = shortest_cycle(&adjacency, &component_set) {
+                let class = classify_cycle(&cycle);
+                let len = cycle.len();
+                let edges = (0..len)
+                    .map(|i| {
+                        let from = cycle[i].0;
+                        let to = cycle[(i + 1) % len].0;
+                        let kind = cycle[i].1;
+                        let description = descriptions
+                            .get(&(from, to, kind))
+                            .cloned()
+                            .unwrap_or_default();
+                        WitnessEdge {
+                            from,
+                            to,
+                            kind,
+                            description,
+                        }
+                    })
+                    .collect();
+                return Some(AnomalyWitness { edges, class });
+            }
+        }
+        None
+    }
+
     pub fn to_dimacs(&self) -> String {
         use std::fmt::Write;
 
@@ -517,12 +957,444 @@ impl Gnf {
     }
 }
 
+/// The dependency-graph edge kinds from Adya's framework, recovered from the
+/// descriptive comment stored alongside each edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum EdgeKind {
+    RealTime,
+    /// Write-write (version order).
+    Ww,
+    /// Write-read (read dependency).
+    Wr,
+    /// Read-write (anti-dependency).
+    Rw,
+}
+
+impl EdgeKind {
+    /// Recover the edge kind from the human-readable comment the edge was added
+    /// with.
+    fn from_comment(comment: &str) -> EdgeKind {
+        if comment.contains("Real time") || comment.contains("Real-time") {
+            EdgeKind::RealTime
+        } else if comment.contains("W-W") {
+            EdgeKind::Ww
+        } else if comment.contains("W-R") {
+            EdgeKind::Wr
+        } else if comment.contains("R-W") {
+            EdgeKind::Rw
+        } else {
+            // Conservatively treat unlabeled edges as anti-dependencies so they
+            // never mask a G2 classification.
+            EdgeKind::Rw
+        }
+    }
+}
+
+/// Adya's cycle taxonomy for a non-serializable history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AnomalyClass {
+    /// A cycle of write-write edges only.
+    G0,
+    /// A cycle mixing write-read and write-write edges (no anti-dependency).
+    G1c,
+    /// A cycle involving at least one read-write anti-dependency edge.
+    G2,
+}
+
+/// A recovered serialization anomaly: the concrete dependency cycle and its
+/// classification.
+#[derive(Debug)]
+struct Anomaly {
+    /// The cycle as a list of `(transaction node, outgoing edge kind)` pairs, in
+    /// order; the last node's edge returns to the first.
+    cycle: Vec<(usize, EdgeKind)>,
+    class: AnomalyClass,
+}
+
 struct KeyAccess {
     transaction_idx: usize,
     value: Option<Vec<u8>>,
 }
 
+/// A single edge of an anomaly witness, carrying the descriptive string it was
+/// added to the graph with so the report names the key and dependency kind.
+#[derive(Debug)]
+struct WitnessEdge {
+    from: usize,
+    to: usize,
+    kind: EdgeKind,
+    description: String,
+}
+
+/// A human-readable report of a guaranteed dependency cycle, produced by
+/// [`Gnf::mandatory_cycle`] without consulting the SAT solver.
+#[derive(Debug)]
+struct AnomalyWitness {
+    edges: Vec<WitnessEdge>,
+    class: AnomalyClass,
+}
+
+impl fmt::Display for AnomalyWitness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nodes: Vec<usize> = self.edges.iter().map(|e| e.from).collect();
+        writeln!(
+            f,
+            "{:?} anomaly: mandatory dependency cycle through transactions {:?}",
+            self.class, nodes
+        )?;
+        for edge in &self.edges {
+            writeln!(
+                f,
+                "  T{} -> T{} ({:?}): {}",
+                edge.from, edge.to, edge.kind, edge.description
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Tarjan's strongly-connected-components algorithm over an adjacency list,
+/// returning the SCCs as lists of node indices.
+fn tarjan_scc(adjacency: &[Vec<(usize, EdgeKind)>]) -> Vec<Vec<usize>> {
+    let n = adjacency.len();
+    let mut index = vec![usize::MAX; n];
+    let mut lowlink = vec![0usize; n];
+    let mut on_stack = vec![false; n];
+    let mut stack = Vec::new();
+    let mut next_index = 0;
+    let mut sccs = Vec::new();
+
+    // Iterative DFS to avoid stack overflow on large graphs.
+    for start in 0..n {
+        if index[start] != usize::MAX {
+            continue;
+        }
+        let mut work: Vec<(usize, usize)> = vec![(start, 0)];
+        while let Some(&(v, child)) = work.last() {
+            if child == 0 {
+                index[v] = next_index;
+                lowlink[v] = next_index;
+                next_index += 1;
+                stack.push(v);
+                on_stack[v] = true;
+            }
+            if child < adjacency[v].len() {
+                let (w, _) = adjacency[v][child];
+                work.last_mut().unwrap().1 += 1;
+                if index[w] == usize::MAX {
+                    work.push((w, 0));
+                } else if on_stack[w] {
+                    lowlink[v] = lowlink[v].min(index[w]);
+                }
+            } else {
+                if lowlink[v] == index[v] {
+                    let mut component = Vec::new();
+                    loop {
+                        let w = stack.pop().unwrap();
+                        on_stack[w] = false;
+                        component.push(w);
+                        if w == v {
+                            break;
+                        }
+                    }
+                    sccs.push(component);
+                }
+                work.pop();
+                if let Some(&(parent, _)) = work.last() {
+                    lowlink[parent] = lowlink[parent].min(lowlink[v]);
+                }
+            }
+        }
+    }
+    sccs
+}
+
+fn has_self_loop(adjacency: &[Vec<(usize, EdgeKind)>], component: &[usize]) -> bool {
+    component
+        .iter()
+        .any(|&v| adjacency[v].iter().any(|&(w, _)| w == v))
+}
+
+/// Find a shortest cycle contained entirely within `component` using a BFS from
+/// each node, returning it as `(node, outgoing edge kind)` pairs.
+fn shortest_cycle(
+    adjacency: &[Vec<(usize, EdgeKind)>],
+    component: &BTreeSet<usize>,
+) -> Option<Vec<(usize, EdgeKind)>> {
+    let mut best: Option<Vec<(usize, EdgeKind)>> = None;
+    for &start in component {
+        // BFS, recording predecessors, to find the shortest path back to start.
+        let mut predecessor: BTreeMap<usize, (usize, EdgeKind)> = BTreeMap::new();
+        let mut queue = std::collections::VecDeque::new();
+        queue.push_back(start);
+        let mut visited = BTreeSet::new();
+        visited.insert(start);
+        'bfs: while let Some(v) = queue.pop_front() {
+            for &(w, kind) in &adjacency[v] {
+                if !component.contains(&w) {
+                    continue;
+                }
+                if w == start {
+                    // Reconstruct the cycle start -> … -> v -> start.
+                    let mut path = vec![(v, kind)];
+                    let mut cur = v;
+                    while cur != start {
+                        let (p, k) = predecessor[&cur];
+                        path.push((p, k));
+                        cur = p;
+                    }
+                    path.reverse();
+                    if best.as_ref().map_or(true, |b| path.len() < b.len()) {
+                        best = Some(path);
+                    }
+                    break 'bfs;
+                }
+                if visited.insert(w) {
+                    predecessor.insert(w, (v, kind));
+                    queue.push_back(w);
+                }
+            }
+        }
+    }
+    best
+}
+
+fn classify_cycle(cycle: &[(usize, EdgeKind)]) -> AnomalyClass {
+    let kinds: Vec<EdgeKind> = cycle.iter().map(|&(_, k)| k).collect();
+    if kinds.iter().any(|&k| k == EdgeKind::Rw) {
+        AnomalyClass::G2
+    } else if kinds.iter().any(|&k| k == EdgeKind::Wr || k == EdgeKind::RealTime) {
+        AnomalyClass::G1c
+    } else {
+        AnomalyClass::G0
+    }
+}
+
+impl EdgeKind {
+    /// Graphviz edge color used when rendering the serialization graph, one per
+    /// dependency kind so the picture is readable at a glance.
+    fn dot_color(self) -> &'static str {
+        match self {
+            EdgeKind::RealTime => "gray",
+            EdgeKind::Ww => "blue",
+            EdgeKind::Wr => "darkgreen",
+            EdgeKind::Rw => "orange",
+        }
+    }
+}
+
+/// Render the serialization graph of a recorded history as a Graphviz DOT
+/// document: one node per transaction labeled with its start/end timestamps and
+/// status, and one edge per candidate dependency colored by kind. When an
+/// `anomaly` is supplied, the edges that make up the violating cycle are drawn
+/// bold and red so an opaque UNSAT result becomes an inspectable graph.
+fn to_dot(
+    transactions: &[(TransactionSpec, TransactionStatus)],
+    gnf: &Gnf,
+    anomaly: Option<&Anomaly>,
+) -> String {
+    use std::fmt::Write;
+
+    // The directed edges that form the recovered cycle, as (from, to) pairs.
+    let mut cycle_edges: BTreeSet<(usize, usize)> = BTreeSet::new();
+    if let Some(anomaly) = anomaly {
+        let cycle = &anomaly.cycle;
+        for i in 0..cycle.len() {
+            let from = cycle[i].0;
+            let to = cycle[(i + 1) % cycle.len()].0;
+            cycle_edges.insert((from, to));
+        }
+    }
+
+    let mut dot = String::from("digraph serialization {\n");
+    writeln!(&mut dot, "    rankdir=LR;").unwrap();
+    writeln!(&mut dot, "    node [shape=box];").unwrap();
+    for (idx, (_, status)) in transactions.iter().enumerate() {
+        let (detail, color) = match status {
+            TransactionStatus::NeverRan => ("never ran".to_string(), "gray"),
+            TransactionStatus::Crashed(TransactionCrashed { start }) => {
+                (format!("crashed @{}", start), "red")
+            }
+            TransactionStatus::Completed(TransactionCompleted { start, end, .. }) => {
+                (format!("[{}, {}]", start, end), "black")
+            }
+        };
+        writeln!(
+            &mut dot,
+            "    T{} [label=\"T{}\\n{}\", color={}];",
+            idx, idx, detail, color
+        )
+        .unwrap();
+    }
+
+    for (edge, comment) in gnf.edges.iter() {
+        let kind = EdgeKind::from_comment(comment);
+        let (from, to) = (edge.from.0, edge.to.0);
+        if cycle_edges.contains(&(from, to)) {
+            writeln!(
+                &mut dot,
+                "    T{} -> T{} [color=red, penwidth=2.0, style=bold];",
+                from, to
+            )
+            .unwrap();
+        } else {
+            writeln!(
+                &mut dot,
+                "    T{} -> T{} [color={}];",
+                from,
+                to,
+                kind.dot_color()
+            )
+            .unwrap();
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
 fn check_history(transactions: &[(TransactionSpec, TransactionStatus)]) -> bool {
+    check_history_at_level(transactions, IsolationLevel::StrictSerializable)
+}
+
+fn check_history_at_level(
+    transactions: &[(TransactionSpec, TransactionStatus)],
+    level: IsolationLevel,
+) -> bool {
+    // Snapshot isolation is checked directly against commit timestamps rather
+    // than through the serialization graph, so it never needs a GNF.
+    if level == IsolationLevel::SnapshotIsolation {
+        return check_snapshot_isolation(transactions);
+    }
+
+    let gnf = match build_history_gnf(transactions, level, &BTreeSet::new()) {
+        None => return false,
+        Some(gnf) => gnf,
+    };
+
+    // Fast path: a cycle among the unconditionally-forced edges is a violation
+    // regardless of the candidate version-order assignment, so report it and
+    // skip the solver.
+    if let Some(witness) = gnf.mandatory_cycle() {
+        eprint!("{}", witness);
+        return false;
+    }
+
+    check_history_with_backend(backend(), &gnf)
+}
+
+/// A committed write to a key, with the closed `[start, end]` interval of the
+/// transaction that made it.
+struct TimestampedWrite {
+    transaction_idx: usize,
+    start: u128,
+    end: u128,
+    value: Option<Vec<u8>>,
+}
+
+/// Snapshot-isolation acceptance check. This does not go through the solver at
+/// all: SI is defined directly in terms of the commit timestamps of committed
+/// transactions, so it is checked directly against those timestamps rather than
+/// via cycle detection in the serialization graph.
+///
+/// A history is SI-valid when, for every key:
+///
+/// - (snapshot-read validity) every read observes the value written by the
+///   write that committed most recently before the reader's own start — i.e.
+///   the write `W` with the greatest `W.end < reader.start`, with no other
+///   committed write interposed between `W.end` and `reader.start`;
+/// - (first-committer-wins) no two committed writes to the key have
+///   overlapping `[start, end]` commit intervals.
+///
+/// Crashed and never-run transactions did not observably commit, so they
+/// contribute no writes and their reads are not checked. Range scans are
+/// predicate reads over a key range rather than a single key's version chain
+/// and are not covered by this check.
+fn check_snapshot_isolation(transactions: &[(TransactionSpec, TransactionStatus)]) -> bool {
+    let mut writes_by_key: BTreeMap<Vec<u8>, Vec<TimestampedWrite>> = BTreeMap::new();
+    for (transaction_idx, (spec, status)) in transactions.iter().enumerate() {
+        let (start, end) = match status {
+            TransactionStatus::Completed(TransactionCompleted { start, end, .. }) => (*start, *end),
+            TransactionStatus::Crashed(_) | TransactionStatus::NeverRan => continue,
+        };
+        for op in &spec.ops {
+            let value = match op {
+                Operation::Insert(InsertOperation { value, .. }) => Some(value.clone()),
+                Operation::Remove(_) => None,
+                Operation::Get(_) | Operation::RangeScan(_) => continue,
+            };
+            writes_by_key
+                .entry(op.key().to_owned())
+                .or_default()
+                .push(TimestampedWrite {
+                    transaction_idx,
+                    start,
+                    end,
+                    value,
+                });
+        }
+    }
+
+    for (key, writes) in &writes_by_key {
+        for (i, a) in writes.iter().enumerate() {
+            for b in &writes[i + 1..] {
+                if a.start <= b.end && b.start <= a.end {
+                    eprintln!(
+                        "Snapshot-isolation anomaly: T{} and T{} both committed writes to key \
+                        {:?} with overlapping snapshots (first-committer-wins violation)",
+                        a.transaction_idx, b.transaction_idx, key,
+                    );
+                    return false;
+                }
+            }
+        }
+    }
+
+    for (transaction_idx, (spec, status)) in transactions.iter().enumerate() {
+        let (reader_start, get_results) = match status {
+            TransactionStatus::Completed(TransactionCompleted {
+                start, get_results, ..
+            }) => (*start, get_results),
+            TransactionStatus::Crashed(_) | TransactionStatus::NeverRan => continue,
+        };
+        for (op_idx, op) in spec.ops.iter().enumerate() {
+            let key = match op {
+                Operation::Get(GetOperation { key }) => key,
+                _ => continue,
+            };
+            let visible_write = writes_by_key
+                .get(key)
+                .into_iter()
+                .flatten()
+                .filter(|write| write.end < reader_start)
+                .max_by_key(|write| write.end);
+            let expected = visible_write.and_then(|write| write.value.clone());
+            if get_results[op_idx] != expected {
+                eprintln!(
+                    "Snapshot-isolation anomaly: T{} read key {:?} as {:?}, but its snapshot \
+                    (taken at start) should observe {:?}",
+                    transaction_idx, key, get_results[op_idx], expected,
+                );
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+/// Build the MonoSAT GNF whose satisfiability is equivalent to the history
+/// admitting a valid version order under `level`. Transactions whose index is in
+/// `removed` are treated as if they never ran: they contribute no edges, their
+/// reads/writes are ignored, and a read whose value only a removed transaction
+/// wrote is dropped with it, which is what lets [`suggest_repair`] probe
+/// whether deleting a set of transactions restores acyclicity. Returns `None`
+/// when the history is trivially impossible (a read observed a value no write
+/// produced), in which case there is no version order to solve for.
+fn build_history_gnf(
+    transactions: &[(TransactionSpec, TransactionStatus)],
+    level: IsolationLevel,
+    removed: &BTreeSet<usize>,
+) -> Option<Gnf> {
     // edges are happens-before/happens-after relations derived from dependency or realtime.
     // serializable: there exists a total order on the transactions that would yield the same results as observed.
     // strictly serializable: there exists a total order on the transactions that obeys real time and that yields the same results as observed.
@@ -537,13 +1409,19 @@ fn check_history(transactions: &[(TransactionSpec, TransactionStatus)]) -> bool
     let mut gnf = Gnf::new();
     gnf.add_clause(
         clause![gnf.acyclic_variable()],
-        "Acyclic property".to_string(),
+        format!("Acyclic property ({})", level.tag()),
     );
     let nodes: Vec<Node> = (0..transactions.len()).map(|_| gnf.add_node()).collect();
 
-    // add real-time edges to graph
+    // add real-time edges to graph (only when the level constrains real time)
     for (i1, t1) in transactions.iter().enumerate() {
         for (i2, t2) in transactions.iter().enumerate() {
+            if !level.uses_real_time() {
+                continue;
+            }
+            if removed.contains(&i1) || removed.contains(&i2) {
+                continue;
+            }
             if let (
                 TransactionStatus::Completed(TransactionCompleted { end: end_1, .. }),
                 TransactionStatus::Completed(TransactionCompleted { start: start_2, .. }),
@@ -569,7 +1447,15 @@ fn check_history(transactions: &[(TransactionSpec, TransactionStatus)]) -> bool
     // build map of which transactions touch each key
     let mut key_to_tx_op: BTreeMap<Vec<u8>, Vec<(usize, usize)>> = BTreeMap::new();
     for (tx_idx, (tx, _)) in transactions.iter().enumerate() {
+        if removed.contains(&tx_idx) {
+            continue;
+        }
         for (op_idx, op) in tx.ops.iter().enumerate() {
+            // Range scans are predicate reads rather than point accesses; their
+            // anti-dependencies are generated in a dedicated pass below.
+            if matches!(op, Operation::RangeScan(_)) {
+                continue;
+            }
             key_to_tx_op
                 .entry(op.key().to_owned())
                 .or_default()
@@ -603,8 +1489,26 @@ fn check_history(transactions: &[(TransactionSpec, TransactionStatus)]) -> bool
                         value: None,
                     });
                 }
+                Operation::RangeScan(_) => {
+                    unreachable!("range scans are not bucketed by point key")
+                }
             }
         }
+        // A read whose value only a removed transaction wrote lost its source
+        // with that transaction; drop it too.
+        reads.retain(|read| {
+            read.value.is_none()
+                || writes.iter().any(|write| write.value == read.value)
+                || !removed.iter().any(|&idx| {
+                    transactions[idx].0.ops.iter().any(|op| {
+                        matches!(op, Operation::Insert(InsertOperation { key: k, value })
+                            if *k == key && Some(value) == read.value.as_ref())
+                    })
+                })
+        });
+        if reads.is_empty() && writes.is_empty() {
+            continue;
+        }
 
         match (writes.len(), reads.len()) {
             (0, 0) => unreachable!(),
@@ -613,7 +1517,7 @@ fn check_history(transactions: &[(TransactionSpec, TransactionStatus)]) -> bool
                 // only okay if the read is None
                 for KeyAccess { value, .. } in reads.iter() {
                     if value.is_some() {
-                        return false;
+                        return None;
                     }
                 }
             }
@@ -635,8 +1539,12 @@ fn check_history(transactions: &[(TransactionSpec, TransactionStatus)]) -> bool
                         (None, None) => {} // no dependency, read could happen before or after the delete.
                         (None, Some(_)) => {
                             dbg!("read value doesn't match write");
-                            return false;
+                            return None;
                         } // impossible
+                        (Some(_), None) if !level.uses_anti_dependencies() => {
+                            // read must happen before the write, but this level does not
+                            // constrain anti-dependencies, so the edge is not added.
+                        }
                         (Some(_), None) => {
                             // read must happen before the write, emit an anti-dependency edge.
                             let variable = gnf.add_variable();
@@ -661,7 +1569,7 @@ fn check_history(transactions: &[(TransactionSpec, TransactionStatus)]) -> bool
                             if *write_value != *read_value {
                                 // impossible, read value came from nowhere.
                                 dbg!("read value doesn't match write");
-                                return false;
+                                return None;
                             } else {
                                 // write must happen before the read, emit an unconditional read
                                 // dependency edge.
@@ -713,7 +1621,7 @@ fn check_history(transactions: &[(TransactionSpec, TransactionStatus)]) -> bool
                     if read_value.is_some() && matching_write_tx_ids.is_empty() {
                         // impossible, read value came from nowhere.
                         dbg!("read value doesn't match any writes");
-                        return false;
+                        return None;
                     }
 
                     // For each candidate write transaction, there's a case with a read dependency
@@ -866,8 +1774,17 @@ fn check_history(transactions: &[(TransactionSpec, TransactionStatus)]) -> bool
                         )
                         .collect::<Vec<String>>()
                         .join(", ");
+                    // For small expressions the distributive normalization is
+                    // cheap and keeps the variable count down; above the
+                    // threshold it can blow up exponentially, so switch to a
+                    // linear-size Tseitin encoding with fresh auxiliary variables.
+                    let clauses = if expr.node_count() > TSEITIN_THRESHOLD {
+                        expr.to_cnf_tseitin(&mut gnf, true)
+                    } else {
+                        expr.to_cnf()
+                    };
                     gnf.add_clauses(
-                        expr.to_cnf(),
+                        clauses,
                         format!(
                             "Ordering of writes [{}] and read T{} on {:?}",
                             writes_str, read_tx_id, key
@@ -878,30 +1795,176 @@ fn check_history(transactions: &[(TransactionSpec, TransactionStatus)]) -> bool
         }
     }
 
+    // Predicate (phantom) anti-dependencies. A range scan constrains not only
+    // the keys it observed but also the keys it did *not*: any later transaction
+    // that inserts or removes a key matching the scan's predicate, yet which does
+    // not appear in the scan's observed result set, must be ordered after the
+    // scan. Emit an R-W anti-dependency edge from the scanning transaction to
+    // each such writer so phantom (G2) anomalies participate in the acyclicity
+    // encoding.
+    if level.uses_anti_dependencies() {
+        for (scan_idx, (scan_tx, scan_status)) in transactions.iter().enumerate() {
+            if removed.contains(&scan_idx) {
+                continue;
+            }
+            if !matches!(scan_status, TransactionStatus::Completed(_)) {
+                continue;
+            }
+            for op in scan_tx.ops.iter() {
+                let (start, end, results) = match op {
+                    Operation::RangeScan(RangeScanOperation { start, end, results }) => {
+                        (start, end, results)
+                    }
+                    _ => continue,
+                };
+                let observed: BTreeSet<&Vec<u8>> = results.iter().map(|(k, _)| k).collect();
+                for (write_idx, (write_tx, _)) in transactions.iter().enumerate() {
+                    if write_idx == scan_idx || removed.contains(&write_idx) {
+                        continue;
+                    }
+                    for write_op in write_tx.ops.iter() {
+                        let write_key = match write_op {
+                            Operation::Insert(InsertOperation { key, .. }) => key,
+                            Operation::Remove(RemoveOperation { key }) => key,
+                            _ => continue,
+                        };
+                        if write_key < start || write_key >= end || observed.contains(write_key) {
+                            continue;
+                        }
+                        let variable = gnf.add_variable();
+                        gnf.add_clause(
+                            clause![variable],
+                            format!(
+                                "Predicate R-W anti-dependency edge from T{} to T{} on {:?}",
+                                scan_idx, write_idx, write_key
+                            ),
+                        );
+                        gnf.add_edge(
+                            nodes[scan_idx],
+                            nodes[write_idx],
+                            variable,
+                            format!(
+                                "Predicate R-W anti-dependency from T{} to T{} on {:?}",
+                                scan_idx, write_idx, write_key
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
     let dimacs = gnf.to_dimacs();
-    match run_monosat(&dimacs) {
-        Ok(Satisfiability::Satisfiable) => true, // found an acyclic graph/valid version order
+    let _ = dimacs;
+    Some(gnf)
+}
+
+/// Run the acyclicity check for an already-built `Gnf` using the chosen SAT
+/// backend, reporting a classified anomaly cycle when the history is not
+/// serializable.
+fn check_history_with_backend(backend: &dyn SatBackend, gnf: &Gnf) -> bool {
+    match backend.solve(gnf) {
+        Ok(Satisfiability::Satisfiable) => true, // acyclic graph/valid version order
         Ok(Satisfiability::Unsatisfiable) => {
-            // there is no valid version order
+            // There is no valid version order; recover and report a concrete
+            // anomaly cycle so the failure is actionable.
+            if let Some(anomaly) = gnf.extract_anomaly(None) {
+                eprintln!(
+                    "{:?} anomaly: cycle through transactions {:?}",
+                    anomaly.class,
+                    anomaly.cycle.iter().map(|&(n, _)| n).collect::<Vec<_>>(),
+                );
+            }
             false
         }
-        Err(e) => panic!("Error running monosat: {}", e),
+        Err(e) => panic!("Error running SAT backend: {}", e),
     }
 }
 
-fn main() -> Result<(), sled::Error> {
-    match run_monosat("") {
-        Err(e) => {
-            eprintln!(
-                "A monosat binary was not found on the PATH, it is required for the \
-                transaction checker. Error: {}",
-                e,
-            );
-            std::process::exit(1);
+/// Solve the history with `removed` transactions deleted, returning whether it
+/// is serializable together with a concrete violating cycle when it is not.
+fn repair_probe(
+    transactions: &[(TransactionSpec, TransactionStatus)],
+    level: IsolationLevel,
+    removed: &BTreeSet<usize>,
+) -> (bool, Option<Anomaly>) {
+    let gnf = match build_history_gnf(transactions, level, removed) {
+        // A read that observed a value no write produced; the history is not
+        // realizable, so deleting this set is not a repair.
+        None => return (false, None),
+        Some(gnf) => gnf,
+    };
+    match backend().solve(&gnf) {
+        Ok(Satisfiability::Satisfiable) => (true, None),
+        Ok(Satisfiability::Unsatisfiable) => (false, gnf.extract_anomaly(None)),
+        Err(e) => panic!("Error running SAT backend: {}", e),
+    }
+}
+
+/// Depth-bounded search for a set of transactions whose removal makes the
+/// serialization graph acyclic. At each step we solve the current graph; if it
+/// is still cyclic we branch on deleting each transaction of a recovered cycle
+/// (the feedback-vertex-set heuristic), spending one unit of `budget` per
+/// deletion.
+fn search_repair(
+    transactions: &[(TransactionSpec, TransactionStatus)],
+    level: IsolationLevel,
+    removed: &mut BTreeSet<usize>,
+    budget: usize,
+) -> Option<BTreeSet<usize>> {
+    let (serializable, anomaly) = repair_probe(transactions, level, removed);
+    if serializable {
+        return Some(removed.clone());
+    }
+    if budget == 0 {
+        return None;
+    }
+    // Branch on the transactions of the recovered cycle; if none was recovered
+    // (version-order constraints alone were unsatisfiable) fall back to every
+    // remaining transaction.
+    let candidates: Vec<usize> = match anomaly {
+        Some(anomaly) => anomaly.cycle.iter().map(|&(n, _)| n).collect(),
+        None => (0..transactions.len())
+            .filter(|i| !removed.contains(i))
+            .collect(),
+    };
+    for candidate in candidates {
+        if !removed.insert(candidate) {
+            continue;
+        }
+        if let Some(witness) = search_repair(transactions, level, removed, budget - 1) {
+            return Some(witness);
         }
-        Ok(Satisfiability::Satisfiable) => {}
-        Ok(_) => unreachable!(),
+        removed.remove(&candidate);
     }
+    None
+}
+
+/// Suggest a minimal repair for a non-serializable history: the smallest set of
+/// transactions whose removal restores serializability. Uses iterative
+/// deepening over the removal budget `k = 1, 2, …` so the first witness found is
+/// of minimal size. Returns an empty vector when the history is already
+/// serializable.
+fn suggest_repair(transactions: &[(TransactionSpec, TransactionStatus)]) -> Vec<usize> {
+    let level = IsolationLevel::StrictSerializable;
+    if repair_probe(transactions, level, &BTreeSet::new()).0 {
+        return Vec::new();
+    }
+    for k in 1..=transactions.len() {
+        let mut removed = BTreeSet::new();
+        if let Some(witness) = search_repair(transactions, level, &mut removed, k) {
+            return witness.into_iter().collect();
+        }
+    }
+    // Removing every transaction is trivially acyclic, so a witness always
+    // exists by the time the budget reaches the transaction count.
+    (0..transactions.len()).collect()
+}
+
+fn main() -> Result<(), sled::Error> {
+    // Prime the backend selection up front so a missing MonoSAT binary is
+    // surfaced once at startup rather than wherever the first solve happens.
+    let _ = backend();
 
     let (crashed_state_directory, stdout_file) = checker_arguments();
     let mut reader = BufReader::new(File::open(stdout_file)?);
@@ -950,7 +2013,19 @@ fn main() -> Result<(), sled::Error> {
                 transaction_idx,
                 end,
                 get_results,
+                range_scan_results,
             }) => {
+                // A scan's result set is only known once the transaction has
+                // actually run, so it isn't in the spec line printed up front;
+                // backfill it here before anything downstream reads the op.
+                for (op_idx, results) in range_scan_results {
+                    if let Operation::RangeScan(range_scan) =
+                        &mut transaction_specs[transaction_idx].ops[op_idx]
+                    {
+                        range_scan.results = results;
+                    }
+                }
+
                 if let Some(old_max_timestamp) = max_timestamp {
                     if end > old_max_timestamp {
                         max_timestamp = Some(end);
@@ -1000,31 +2075,51 @@ fn main() -> Result<(), sled::Error> {
     let mut all_keys = BTreeSet::new();
     for spec in transaction_specs.iter() {
         for op in spec.ops.iter() {
-            let key = match op {
-                Operation::Get(GetOperation { key }) => key.clone(),
-                Operation::Insert(InsertOperation { key, .. }) => key.clone(),
-                Operation::Remove(RemoveOperation { key }) => key.clone(),
-            };
-            all_keys.insert(key);
+            match op {
+                Operation::Get(GetOperation { key }) => {
+                    all_keys.insert(key.clone());
+                }
+                Operation::Insert(InsertOperation { key, .. }) => {
+                    all_keys.insert(key.clone());
+                }
+                Operation::Remove(RemoveOperation { key }) => {
+                    all_keys.insert(key.clone());
+                }
+                // A range scan reads every key it observed; include those so the
+                // final point-read faux transaction covers them too.
+                Operation::RangeScan(RangeScanOperation { results, .. }) => {
+                    for (key, _value) in results.iter() {
+                        all_keys.insert(key.clone());
+                    }
+                }
+            }
         }
     }
 
-    // Confirm there are no keys appearing ex nihilo
+    // Confirm there are no keys appearing ex nihilo, caching the most recently
+    // seen entries so the point-read pass below can serve hot keys without a
+    // second trip to the tree.
+    let mut read_cache: FixedVecDeque<(Vec<u8>, Option<Vec<u8>>), 256> = FixedVecDeque::new();
     for res in db.iter() {
-        let (key, _value) = res?;
-        if !all_keys.contains(&*key) {
+        let (key, value) = res?;
+        let key = key.as_ref().to_owned();
+        if !all_keys.contains(&key) {
             panic!(
                 "Key in database did not appear in any transaction: {:?}",
                 key
             );
         }
+        read_cache.push_back((key, Some(value.as_ref().to_owned())));
     }
 
     // Build a faux transaction/result from all the point reads
     let mut point_read_tx_spec = TransactionSpec { ops: Vec::new() };
     let mut get_results = Vec::with_capacity(all_keys.len());
     for key in all_keys {
-        let get_result = db.get(&key)?.map(|ivec| ivec.as_ref().to_owned());
+        let get_result = match read_cache.iter().find(|(k, _)| *k == key) {
+            Some((_, value)) => value.clone(),
+            None => db.get(&key)?.map(|ivec| ivec.as_ref().to_owned()),
+        };
         point_read_tx_spec
             .ops
             .push(Operation::Get(GetOperation { key }));
@@ -1045,8 +2140,47 @@ fn main() -> Result<(), sled::Error> {
         .zip(transaction_results.into_iter())
         .collect();
 
-    if !check_history(&transactions) {
-        panic!("Problem in transaction history");
+    // The guarantee to check against defaults to strict serializability but can
+    // be lowered via the environment so one recorded history can be audited at
+    // several levels without rebuilding the workload.
+    let level = match std::env::var("SLED_ISOLATION_LEVEL") {
+        Ok(tag) => IsolationLevel::from_tag(&tag)
+            .unwrap_or_else(|| panic!("Unknown isolation level: {}", tag)),
+        Err(_) => IsolationLevel::StrictSerializable,
+    };
+
+    if !check_history_at_level(&transactions, level) {
+        // Surface a minimal-repair hint before failing, so an opaque
+        // violation comes with something actionable instead of just a panic.
+        let repair = suggest_repair(&transactions);
+        if repair.is_empty() {
+            eprintln!(
+                "no repair hint: the history already passes the {} check",
+                IsolationLevel::StrictSerializable.tag()
+            );
+        } else {
+            eprintln!(
+                "hint: removing transactions {:?} restores {}",
+                repair,
+                IsolationLevel::StrictSerializable.tag()
+            );
+        }
+
+        // `SLED_DOT_OUTPUT` exports the serialization graph as Graphviz DOT,
+        // with the recovered anomaly cycle highlighted, so the failure is
+        // inspectable rather than just a transaction-index list.
+        if let Ok(path) = std::env::var("SLED_DOT_OUTPUT") {
+            if let Some(gnf) = build_history_gnf(&transactions, level, &BTreeSet::new()) {
+                let anomaly = gnf.extract_anomaly(None);
+                if let Err(e) =
+                    std::fs::write(&path, to_dot(&transactions, &gnf, anomaly.as_ref()))
+                {
+                    eprintln!("failed to write {}: {}", path, e);
+                }
+            }
+        }
+
+        panic!("Problem in transaction history (checked at {})", level.tag());
     }
 
     Ok(())
@@ -1074,7 +2208,7 @@ mod tests {
             acyclic 0 4\n\
             ",
         );
-        assert_eq!(res.unwrap(), Satisfiability::Unsatisfiable);
+        assert_eq!(res.unwrap().0, Satisfiability::Unsatisfiable);
 
         let res = run_monosat(
             "p cnf 4 2\n\
@@ -1087,7 +2221,106 @@ mod tests {
             acyclic 0 4\n\
             ",
         );
-        assert_eq!(res.unwrap(), Satisfiability::Satisfiable);
+        assert_eq!(res.unwrap().0, Satisfiability::Satisfiable);
+    }
+
+    #[test]
+    fn test_chunk_merge_wrap() {
+        use sled_workload_transactions::{ChunkMerge, FixedVecDeque};
+
+        // Drive a ring buffer into a wrapped state so its live region spans the
+        // end of the backing array: oldest-to-newest is [3, 4, 5, 6] but stored
+        // as the two slices ([3, 4], [5, 6]).
+        let mut keys: FixedVecDeque<u32, 4> = FixedVecDeque::new();
+        for v in [1, 2, 3, 4] {
+            keys.push_back(v);
+        }
+        keys.pop_front();
+        keys.pop_front();
+        keys.push_back(5);
+        keys.push_back(6);
+        let (key_front, key_back) = keys.as_slices();
+        assert!(!key_back.is_empty(), "ring buffer should be wrapped");
+
+        // A contiguous second source carrying the matching get_results.
+        let results: Vec<Option<u32>> = vec![Some(30), Some(40), Some(50), Some(60)];
+
+        let merged: Vec<(u32, u32)> = ChunkMerge::new((key_front, key_back), (&results, &[]))
+            .flat_map(|(ks, vs)| {
+                ks.iter()
+                    .zip(vs.iter())
+                    .map(|(k, v)| (k.unwrap(), v.unwrap()))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        assert_eq!(merged, vec![(3, 30), (4, 40), (5, 50), (6, 60)]);
+    }
+
+    #[test]
+    fn test_fixed_vec_deque_eviction() {
+        use sled_workload_transactions::FixedVecDeque;
+
+        let mut buf: FixedVecDeque<u32, 3> = FixedVecDeque::new();
+        assert_eq!(buf.push_back(1), None);
+        assert_eq!(buf.push_back(2), None);
+        assert_eq!(buf.push_back(3), None);
+        assert!(buf.is_full());
+        // Overflowing evicts and returns the oldest element.
+        assert_eq!(buf.push_back(4), Some(1));
+        assert_eq!(buf.iter().copied().collect::<Vec<_>>(), vec![2, 3, 4]);
+        assert_eq!(buf.pop_front(), Some(2));
+        assert_eq!(buf.iter().copied().collect::<Vec<_>>(), vec![3, 4]);
+    }
+
+    #[test]
+    fn test_phantom_anti_dependency() {
+        use sled_workload_transactions::RangeScanOperation;
+
+        // T0 read T1's write to key [1] (a W-R dependency T1 -> T0) but its scan
+        // of the whole range failed to observe T1's insert of key [2] (a predicate
+        // R-W anti-dependency T0 -> T1). The two forced edges form a phantom (G2)
+        // cycle, so the history is not serializable.
+        let history = vec![
+            (
+                TransactionSpec {
+                    ops: vec![
+                        Operation::Get(GetOperation { key: vec![1] }),
+                        Operation::RangeScan(RangeScanOperation {
+                            start: vec![0],
+                            end: vec![255],
+                            results: vec![(vec![1], vec![9])],
+                        }),
+                    ],
+                },
+                TransactionStatus::Completed(TransactionCompleted {
+                    start: 0,
+                    end: 1,
+                    get_results: vec![Some(vec![9]), None],
+                }),
+            ),
+            (
+                TransactionSpec {
+                    ops: vec![
+                        Operation::Insert(InsertOperation {
+                            key: vec![1],
+                            value: vec![9],
+                        }),
+                        Operation::Insert(InsertOperation {
+                            key: vec![2],
+                            value: vec![8],
+                        }),
+                    ],
+                },
+                TransactionStatus::Completed(TransactionCompleted {
+                    start: 0,
+                    end: 1,
+                    get_results: vec![None, None],
+                }),
+            ),
+        ];
+
+        assert!(!check_history(&history));
     }
 
     #[test]
@@ -1109,7 +2342,7 @@ mod tests {
             gnf.add_clause(clause![gnf.acyclic_variable()], "".to_string());
             let dimacs = gnf.to_dimacs();
             let res = run_monosat(&dimacs);
-            assert_eq!(res.unwrap(), Satisfiability::Unsatisfiable);
+            assert_eq!(res.unwrap().0, Satisfiability::Unsatisfiable);
         }
         {
             let mut gnf = Gnf::new();
@@ -1141,10 +2374,53 @@ mod tests {
             );
             let dimacs = gnf.to_dimacs();
             let res = run_monosat(&dimacs);
-            assert_eq!(res.unwrap(), Satisfiability::Satisfiable);
+            assert_eq!(res.unwrap().0, Satisfiability::Satisfiable);
         }
     }
 
+    #[test]
+    fn test_dot_export() {
+        use crate::{to_dot, Anomaly, AnomalyClass, EdgeKind};
+
+        let transactions = vec![
+            (
+                TransactionSpec { ops: vec![] },
+                TransactionStatus::Completed(TransactionCompleted {
+                    start: 0,
+                    end: 1,
+                    get_results: vec![],
+                }),
+            ),
+            (
+                TransactionSpec { ops: vec![] },
+                TransactionStatus::Completed(TransactionCompleted {
+                    start: 2,
+                    end: 3,
+                    get_results: vec![],
+                }),
+            ),
+        ];
+
+        let mut gnf = Gnf::new();
+        let n0 = gnf.add_node();
+        let n1 = gnf.add_node();
+        let v0 = gnf.add_variable();
+        let v1 = gnf.add_variable();
+        gnf.add_edge(n0, n1, v0, "W-R dependency from T0 to T1".to_string());
+        gnf.add_edge(n1, n0, v1, "R-W anti-dependency from T1 to T0".to_string());
+
+        let anomaly = Anomaly {
+            cycle: vec![(0, EdgeKind::Wr), (1, EdgeKind::Rw)],
+            class: AnomalyClass::G2,
+        };
+        let dot = to_dot(&transactions, &gnf, Some(&anomaly));
+
+        assert!(dot.contains("T0 [label=\"T0\\n[0, 1]\""));
+        // Both directed edges of the cycle are highlighted.
+        assert!(dot.contains("T0 -> T1 [color=red"));
+        assert!(dot.contains("T1 -> T0 [color=red"));
+    }
+
     #[test]
     fn test_tx_cycle() {
         assert!(!check_history(&[
@@ -1182,4 +2458,192 @@ mod tests {
             ),
         ]));
     }
+
+    #[test]
+    fn test_mandatory_cycle() {
+        let mut gnf = Gnf::new();
+        let n0 = gnf.add_node();
+        let n1 = gnf.add_node();
+        let v0 = gnf.add_variable();
+        let v1 = gnf.add_variable();
+        gnf.add_edge(n0, n1, v0, "W-R dependency from T0 to T1 on []".to_string());
+        gnf.add_edge(n1, n0, v1, "W-R dependency from T1 to T0 on []".to_string());
+        // Both edges are forced true by positive unit clauses, so the cycle is
+        // mandatory and detectable without the solver.
+        gnf.add_clause(clause![v0], "edge T0->T1".to_string());
+        gnf.add_clause(clause![v1], "edge T1->T0".to_string());
+
+        let witness = gnf.mandatory_cycle().expect("cycle should be detected");
+        assert_eq!(witness.edges.len(), 2);
+        let report = witness.to_string();
+        assert!(report.contains("W-R dependency"));
+    }
+
+    #[test]
+    fn test_si_write_skew_is_admitted() {
+        use crate::check_snapshot_isolation;
+
+        // Classic write skew: T0 and T1 both read keys `a` and `b` as absent
+        // under their own snapshot, then each writes a different one of them. This is not
+        // serializable, but SI permits it (each snapshot read and each write's
+        // commit interval is individually valid), so the check must accept it.
+        let history = vec![
+            (
+                TransactionSpec {
+                    ops: vec![
+                        Operation::Get(GetOperation { key: vec![b'a'] }),
+                        Operation::Get(GetOperation { key: vec![b'b'] }),
+                        Operation::Insert(InsertOperation {
+                            key: vec![b'a'],
+                            value: vec![1],
+                        }),
+                    ],
+                },
+                TransactionStatus::Completed(TransactionCompleted {
+                    start: 0,
+                    end: 2,
+                    get_results: vec![None, None, None],
+                }),
+            ),
+            (
+                TransactionSpec {
+                    ops: vec![
+                        Operation::Get(GetOperation { key: vec![b'a'] }),
+                        Operation::Get(GetOperation { key: vec![b'b'] }),
+                        Operation::Insert(InsertOperation {
+                            key: vec![b'b'],
+                            value: vec![1],
+                        }),
+                    ],
+                },
+                TransactionStatus::Completed(TransactionCompleted {
+                    start: 0,
+                    end: 2,
+                    get_results: vec![None, None, None],
+                }),
+            ),
+        ];
+
+        assert!(check_snapshot_isolation(&history));
+    }
+
+    #[test]
+    fn test_si_rejects_overlapping_writes_to_same_key() {
+        use crate::check_snapshot_isolation;
+
+        // Two transactions with overlapping snapshots both commit a write to
+        // the same key; first-committer-wins forbids this.
+        let history = vec![
+            (
+                TransactionSpec {
+                    ops: vec![Operation::Insert(InsertOperation {
+                        key: vec![b'a'],
+                        value: vec![1],
+                    })],
+                },
+                TransactionStatus::Completed(TransactionCompleted {
+                    start: 0,
+                    end: 2,
+                    get_results: vec![None],
+                }),
+            ),
+            (
+                TransactionSpec {
+                    ops: vec![Operation::Insert(InsertOperation {
+                        key: vec![b'a'],
+                        value: vec![2],
+                    })],
+                },
+                TransactionStatus::Completed(TransactionCompleted {
+                    start: 1,
+                    end: 3,
+                    get_results: vec![None],
+                }),
+            ),
+        ];
+
+        assert!(!check_snapshot_isolation(&history));
+    }
+
+    #[test]
+    fn test_si_rejects_stale_snapshot_read() {
+        use crate::check_snapshot_isolation;
+
+        // T1 starts after T0 has committed its write to `a`, so T1's snapshot
+        // must observe it; recording `None` instead is a stale read.
+        let history = vec![
+            (
+                TransactionSpec {
+                    ops: vec![Operation::Insert(InsertOperation {
+                        key: vec![b'a'],
+                        value: vec![1],
+                    })],
+                },
+                TransactionStatus::Completed(TransactionCompleted {
+                    start: 0,
+                    end: 1,
+                    get_results: vec![None],
+                }),
+            ),
+            (
+                TransactionSpec {
+                    ops: vec![Operation::Get(GetOperation { key: vec![b'a'] })],
+                },
+                TransactionStatus::Completed(TransactionCompleted {
+                    start: 2,
+                    end: 3,
+                    get_results: vec![None],
+                }),
+            ),
+        ];
+
+        assert!(!check_snapshot_isolation(&history));
+    }
+
+    #[test]
+    fn test_suggest_repair() {
+        use crate::suggest_repair;
+
+        // The same cycle as `test_tx_cycle`: each transaction read the other's
+        // written value (two W-R edges), so removing either one breaks the
+        // cycle and the minimal repair is a single transaction.
+        let history = vec![
+            (
+                TransactionSpec {
+                    ops: vec![
+                        Operation::Get(GetOperation { key: vec![] }),
+                        Operation::Insert(InsertOperation {
+                            key: vec![],
+                            value: vec![1],
+                        }),
+                    ],
+                },
+                TransactionStatus::Completed(TransactionCompleted {
+                    start: 0,
+                    end: 1,
+                    get_results: vec![Some(vec![2]), None],
+                }),
+            ),
+            (
+                TransactionSpec {
+                    ops: vec![
+                        Operation::Get(GetOperation { key: vec![] }),
+                        Operation::Insert(InsertOperation {
+                            key: vec![],
+                            value: vec![2],
+                        }),
+                    ],
+                },
+                TransactionStatus::Completed(TransactionCompleted {
+                    start: 0,
+                    end: 1,
+                    get_results: vec![Some(vec![1]), None],
+                }),
+            ),
+        ];
+
+        let repair = suggest_repair(&history);
+        assert_eq!(repair.len(), 1);
+        assert!(repair[0] == 0 || repair[0] == 1);
+    }
 }
@@ -0,0 +1,241 @@
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{self, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use sled_workload_transactions::*;
+
+// Integrated crash-injection + durability oracle for `transactions_workload`.
+//
+// In run mode this driver spawns the workload as a child process, streams every
+// committed `TransactionEndOutput` to a separate fsync'd "intent log", and sends
+// the child `SIGKILL` at a caller-specified (or randomized) point mid-run. It
+// then reopens the sled database and replays the intent log to assert that every
+// transaction recorded as `End` before the kill is fully durable — all of its
+// `Insert`s present with the recorded value, all of its `Remove`s absent.
+// Transactions still in flight at the kill are not in the intent log, and
+// their keys are not checked for partially applied effects.
+//
+// `--replay <dir> <log>` runs only the verification phase against an
+// already-crashed directory and a previously captured intent log, which
+// generalizes the fixed shifted-bytes checks into a full transactional
+// durability oracle.
+
+/// The durable effect of a single committed transaction, captured when its
+/// `End` output is observed. Recording the resolved key/value effects — rather
+/// than just the transaction index — keeps the intent log self-contained so
+/// `--replay` needs nothing but the log and the crashed directory.
+#[derive(Serialize, Deserialize)]
+struct CommittedTransaction {
+    transaction_idx: usize,
+    inserts: Vec<(Vec<u8>, Vec<u8>)>,
+    removes: Vec<Vec<u8>>,
+}
+
+impl CommittedTransaction {
+    /// Resolve a committed transaction's spec into the concrete set of keys it
+    /// must have persisted and the keys it must have deleted.
+    fn from_spec(transaction_idx: usize, spec: &TransactionSpec) -> CommittedTransaction {
+        let mut inserts = Vec::new();
+        let mut removes = Vec::new();
+        for op in &spec.ops {
+            match op {
+                Operation::Insert(InsertOperation { key, value }) => {
+                    inserts.push((key.clone(), value.clone()));
+                }
+                Operation::Remove(RemoveOperation { key }) => removes.push(key.clone()),
+                // Reads and range scans leave no durable effect to verify.
+                Operation::Get(_) | Operation::RangeScan(_) => {}
+            }
+        }
+        CommittedTransaction {
+            transaction_idx,
+            inserts,
+            removes,
+        }
+    }
+}
+
+/// Reopen the database at `dir` and assert that every committed transaction in
+/// `log` is durable: each insert present with the recorded value, each remove
+/// absent. A later committed transaction that overwrote or re-inserted the same
+/// key wins, so effects are applied in commit order before the final assertion.
+fn replay(dir: &str, log: &str) -> Result<(), sled::Error> {
+    let db = config(dir, CACHE_CAPACITY, SEGMENT_SIZE, false).open()?;
+
+    // Fold the committed effects in commit order into the key/value state they
+    // must leave behind; the last writer of each key wins.
+    let mut expected: BTreeMap<Vec<u8>, Option<Vec<u8>>> = BTreeMap::new();
+    let reader = BufReader::new(File::open(log)?);
+    let mut committed = 0usize;
+    for line in reader.lines() {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        let tx: CommittedTransaction = serde_json::from_str(&line).unwrap();
+        committed += 1;
+        for (key, value) in tx.inserts {
+            expected.insert(key, Some(value));
+        }
+        for key in tx.removes {
+            expected.insert(key, None);
+        }
+    }
+
+    for (key, value) in &expected {
+        let durable = db.get(key)?.map(|v| v.as_ref().to_owned());
+        match value {
+            Some(value) => assert_eq!(
+                durable.as_ref(),
+                Some(value),
+                "committed insert for key {:?} is not durable",
+                key
+            ),
+            None => assert_eq!(
+                durable, None,
+                "committed remove for key {:?} is still visible",
+                key
+            ),
+        }
+    }
+
+    eprintln!("verified {} committed transactions durable", committed);
+    Ok(())
+}
+
+fn main() {
+    let matches = App::new("transactions_crash")
+        .version(crate_version!())
+        .arg(
+            Arg::with_name("replay")
+                .long("replay")
+                .required(false)
+                .number_of_values(2)
+                .value_names(&["dir", "log"]),
+        )
+        .arg(
+            Arg::with_name("transactions")
+                .index(1)
+                .required(false)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("crash_after_ms")
+                .long("crash-after-ms")
+                .required(false)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("crash_after_txns")
+                .long("crash-after-txns")
+                .required(false)
+                .takes_value(true),
+        )
+        .get_matches();
+
+    if let Some(mut values) = matches.values_of("replay") {
+        let dir = values.next().unwrap();
+        let log = values.next().unwrap();
+        replay(dir, log).unwrap();
+        return;
+    }
+
+    let transaction_count = matches.value_of("transactions").unwrap_or("1000");
+    let crash_after_ms: Option<u64> = matches
+        .value_of("crash_after_ms")
+        .map(|ms| ms.parse().expect("crash-after-ms must be an integer"));
+    let crash_after_txns: Option<usize> = matches
+        .value_of("crash_after_txns")
+        .map(|n| n.parse().expect("crash-after-txns must be an integer"));
+    // With neither crash point given, kill after a random number of commits.
+    let crash_after_txns = match (crash_after_ms, crash_after_txns) {
+        (None, None) => {
+            let count: usize = transaction_count.parse().unwrap_or(1000);
+            Some(rand::Rng::gen_range(&mut rand::thread_rng(), 0..count.max(1)))
+        }
+        (_, crash_after_txns) => crash_after_txns,
+    };
+
+    let log_path = "transactions_intent.log";
+    let mut intent_log = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(log_path)
+        .unwrap();
+
+    // Resolve the workload binary next to this one so the driver works from a
+    // `target/` layout without a hard-coded path.
+    let mut workload_bin = std::env::current_exe().unwrap();
+    workload_bin.set_file_name("transactions_workload");
+
+    let mut child = Command::new(&workload_bin)
+        .arg(transaction_count)
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let stdout = child.stdout.take().unwrap();
+    let mut lines = BufReader::new(stdout).lines();
+
+    // The first line is the `Vec<TransactionSpec>`; keep it to resolve each
+    // committed transaction's effects as its `End` output streams in.
+    let specs_line = lines.next().expect("workload produced no output").unwrap();
+    let specs: Vec<TransactionSpec> = serde_json::from_str(&specs_line).unwrap();
+
+    // Arm a wall-clock crash if requested; the flag fires from a timer thread so
+    // the read loop below can observe it between committed transactions.
+    let killed = Arc::new(AtomicBool::new(false));
+    if let Some(ms) = crash_after_ms {
+        let killed = Arc::clone(&killed);
+        let deadline = Instant::now() + Duration::from_millis(ms);
+        thread::spawn(move || {
+            let now = Instant::now();
+            if deadline > now {
+                thread::sleep(deadline - now);
+            }
+            killed.store(true, Ordering::SeqCst);
+        });
+    }
+
+    let mut committed = 0usize;
+    for line in lines {
+        let line = line.unwrap();
+        if line.is_empty() {
+            continue;
+        }
+        if let Ok(TransactionOutput::End(end)) = serde_json::from_str::<TransactionOutput>(&line) {
+            let spec = &specs[end.transaction_idx];
+            let record = CommittedTransaction::from_spec(end.transaction_idx, spec);
+            writeln!(intent_log, "{}", serde_json::to_string(&record).unwrap()).unwrap();
+            // fsync the intent log before acknowledging the commit, so a crash
+            // can never lose a record of a transaction the database kept.
+            intent_log.flush().unwrap();
+            intent_log.sync_all().unwrap();
+            committed += 1;
+        }
+        let hit_txn_limit = crash_after_txns.map_or(false, |n| committed >= n);
+        if hit_txn_limit || killed.load(Ordering::SeqCst) {
+            break;
+        }
+    }
+
+    // Deliver SIGKILL mid-run (on Unix `Child::kill` sends SIGKILL) so the
+    // database is left in whatever on-disk state the crash produced.
+    let _ = child.kill();
+    let _ = child.wait();
+
+    eprintln!("crashed after {} committed transactions", committed);
+    drop(intent_log);
+
+    if let Err(e) = replay(WORKLOAD_DIR, log_path) {
+        eprintln!("durability verification failed: {}", e);
+        process::exit(1);
+    }
+}
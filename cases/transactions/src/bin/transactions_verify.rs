@@ -0,0 +1,79 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::process;
+
+use sled_workload_transactions::*;
+
+// Consumes the JSON history emitted by `transactions_workload` — the first line
+// is the `Vec<TransactionSpec>`, every subsequent line a `TransactionOutput` —
+// and decides whether the observed results are serializable under a real-time
+// ordering constraint. Reads from a file argument if given, otherwise stdin.
+
+fn main() {
+    let matches = App::new("transactions_verify")
+        .version(crate_version!())
+        .arg(
+            Arg::with_name("history")
+                .index(1)
+                .required(false)
+                .takes_value(true),
+        )
+        .get_matches();
+
+    let reader: Box<dyn BufRead> = match matches.value_of("history") {
+        Some(path) => Box::new(BufReader::new(File::open(path).unwrap())),
+        None => Box::new(BufReader::new(io::stdin())),
+    };
+
+    let mut lines = reader.lines();
+    let specs_line = match lines.next() {
+        Some(line) => line.unwrap(),
+        None => {
+            eprintln!("empty history");
+            process::exit(1);
+        }
+    };
+    let mut specs: Vec<TransactionSpec> = serde_json::from_str(&specs_line).unwrap();
+
+    let mut history = Vec::new();
+    for line in lines {
+        let line = line.unwrap();
+        if line.is_empty() {
+            continue;
+        }
+        let output = serde_json::from_str::<TransactionOutput>(&line).unwrap();
+        // Scan results arrive only in the `End` output; backfill them into the
+        // spec so the checks below compare against what each scan observed.
+        if let TransactionOutput::End(end) = &output {
+            for (op_idx, results) in &end.range_scan_results {
+                if let Operation::RangeScan(scan) = &mut specs[end.transaction_idx].ops[*op_idx] {
+                    scan.results = results.clone();
+                }
+            }
+        }
+        history.push(output);
+    }
+
+    match find_serialization(&specs, &history) {
+        Ok(order) => {
+            println!("serializable; linearization: {:?}", order);
+        }
+        Err(anomaly) => {
+            eprintln!("history is not serializable: {:?}", anomaly);
+            process::exit(1);
+        }
+    }
+
+    // A second, independent check of the same history: `linearize` runs the
+    // Wing–Gong search, treating each transaction as one operation and, like
+    // the check above, only admitting orders that respect real-time precedence.
+    match linearize(&specs, &history) {
+        Ok(()) => {
+            println!("linearizable");
+        }
+        Err(anomaly) => {
+            eprintln!("history is not linearizable: {:?}", anomaly);
+            process::exit(1);
+        }
+    }
+}
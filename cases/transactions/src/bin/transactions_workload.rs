@@ -1,4 +1,5 @@
 use std::{
+    cell::RefCell,
     collections::BTreeSet,
     convert::TryInto,
     process,
@@ -7,7 +8,8 @@ use std::{
     time::Instant,
 };
 
-use rand::{distributions::Distribution, Rng};
+use rand::{distributions::Distribution, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 
 use sled_workload_transactions::*;
 
@@ -18,6 +20,11 @@ const DEFAULT_CARDINALITY: usize = 25;
 const DEFAULT_MAX_BYTE_LENGTH: usize = 512;
 const DEFAULT_WRITE_PROBABILITY: f64 = 0.4;
 const DEFAULT_DELETE_PROBABILITY: f64 = 0.1;
+const DEFAULT_ZIPF_EXPONENT: f64 = 1.0;
+const DEFAULT_RANGE_SCAN_PROBABILITY: f64 = 0.15;
+/// Upper bound on how many keys a generated range scan spans, so the scan
+/// stays cheap relative to the point ops around it.
+const MAX_RANGE_SCAN_SPAN: usize = 5;
 
 fn bytes_factory<R: Rng>(rng: &mut R, max_byte_length: usize) -> Vec<u8> {
     let beta_statistic = rand_distr::Beta::new(1.2, 10.0).unwrap().sample(rng);
@@ -41,6 +48,70 @@ fn build_key_space<R: Rng>(
     keys.into_iter().collect()
 }
 
+/// The half-open `[start, end)` bounds of a scan over `span` consecutive keys
+/// of `key_space` starting at `start_idx`. `key_space` is sorted, so the bound
+/// after the last key in the span is either the next key in the space or, if
+/// the span runs off the end, a byte string strictly greater than the last key
+/// (any key is a proper prefix of itself with a byte appended).
+fn range_scan_bounds(key_space: &[Vec<u8>], start_idx: usize, span: usize) -> (Vec<u8>, Vec<u8>) {
+    let start = key_space[start_idx].clone();
+    let end_idx = start_idx + span;
+    let end = if end_idx < key_space.len() {
+        key_space[end_idx].clone()
+    } else {
+        let mut end = key_space[key_space.len() - 1].clone();
+        end.push(0xff);
+        end
+    };
+    (start, end)
+}
+
+/// How key indices are drawn when building transactions.
+#[derive(Clone, Copy)]
+enum KeyDistribution {
+    /// Every key equally likely.
+    Uniform,
+    /// Skewed toward low-index keys, modeling a small set of hot keys.
+    Zipf,
+}
+
+/// Precompute the normalized cumulative Zipf weights over `0..cardinality`:
+/// `w_i = (1/(i+1)^s) / H` with `H = Σ_j 1/(j+1)^s`, returned as a running sum so
+/// a key can be sampled by binary search against a uniform `[0, 1)` draw.
+fn zipf_cumulative(cardinality: usize, exponent: f64) -> Vec<f64> {
+    let weights: Vec<f64> = (0..cardinality)
+        .map(|i| 1.0 / ((i + 1) as f64).powf(exponent))
+        .collect();
+    let normalizer: f64 = weights.iter().sum();
+    let mut cumulative = Vec::with_capacity(cardinality);
+    let mut acc = 0.0;
+    for weight in weights {
+        acc += weight / normalizer;
+        cumulative.push(acc);
+    }
+    cumulative
+}
+
+/// Sample a key index according to `distribution`. For `Zipf`, `cumulative` is
+/// the array from [`zipf_cumulative`] and the index is the first cumulative
+/// weight strictly above a uniform draw.
+fn sample_key_index<R: Rng>(
+    rng: &mut R,
+    distribution: KeyDistribution,
+    cumulative: &[f64],
+    cardinality: usize,
+) -> usize {
+    match distribution {
+        KeyDistribution::Uniform => rng.gen_range(0..cardinality),
+        KeyDistribution::Zipf => {
+            let draw: f64 = rng.gen_range(0.0..1.0);
+            cumulative
+                .partition_point(|&weight| weight <= draw)
+                .min(cardinality - 1)
+        }
+    }
+}
+
 fn main() {
     let matches = App::new("transactions_workload")
         .version(crate_version!())
@@ -87,6 +158,37 @@ fn main() {
                 .required(false)
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .required(false)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("key_distribution")
+                .long("key_distribution")
+                .required(false)
+                .takes_value(true)
+                .possible_values(&["uniform", "zipf"]),
+        )
+        .arg(
+            Arg::with_name("zipf_exponent")
+                .long("zipf_exponent")
+                .required(false)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("range_scan_probability")
+                .long("range_scan_probability")
+                .required(false)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("tempdir")
+                .long("tempdir")
+                .required(false)
+                .takes_value(false),
+        )
         .get_matches();
     let transaction_count = if let Some(transactions) = matches.value_of("transactions") {
         if let Ok(transactions) = transactions.parse() {
@@ -164,6 +266,31 @@ fn main() {
         } else {
             DEFAULT_DELETE_PROBABILITY
         };
+    let zipf_exponent = if let Some(zipf_exponent) = matches.value_of("zipf_exponent") {
+        if let Ok(zipf_exponent) = zipf_exponent.parse() {
+            zipf_exponent
+        } else {
+            eprintln!("{}", matches.usage());
+            process::exit(1);
+        }
+    } else {
+        DEFAULT_ZIPF_EXPONENT
+    };
+    let key_distribution = match matches.value_of("key_distribution") {
+        Some("zipf") => KeyDistribution::Zipf,
+        _ => KeyDistribution::Uniform,
+    };
+    let range_scan_probability =
+        if let Some(range_scan_probability) = matches.value_of("range_scan_probability") {
+            if let Ok(range_scan_probability) = range_scan_probability.parse() {
+                range_scan_probability
+            } else {
+                eprintln!("{}", matches.usage());
+                process::exit(1);
+            }
+        } else {
+            DEFAULT_RANGE_SCAN_PROBABILITY
+        };
 
     // Generate transactions consisting of random operations.
     // Constraints:
@@ -171,14 +298,45 @@ fn main() {
     // * Each transaction writes any given key at most once
     // * One transaction can't read and write to the same key
     // (then order of operations within a transaction would matter, and that's annoying)
-    let mut rng = rand::thread_rng();
+    // Seed a ChaCha generator so a run can be replayed byte-for-byte: an
+    // explicit `--seed` reproduces a prior run, otherwise a fresh seed is drawn
+    // and logged to stderr for later replay.
+    let seed: u64 = if let Some(seed) = matches.value_of("seed") {
+        if let Ok(seed) = seed.parse() {
+            seed
+        } else {
+            eprintln!("{}", matches.usage());
+            process::exit(1);
+        }
+    } else {
+        rand::thread_rng().gen()
+    };
+    eprintln!("seed={}", seed);
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
     let key_space = build_key_space(&mut rng, max_byte_length, cardinality);
+    let zipf_cumulative = zipf_cumulative(key_space.len(), zipf_exponent);
     let mut transactions = Vec::with_capacity(transaction_count);
     transactions.resize_with(transaction_count, || {
         let mut ops = Vec::with_capacity(ops_per_tx);
         let mut keys_used = BTreeSet::new();
         while ops.len() < ops_per_tx && ops.len() < key_space.len() {
-            let key_idx = rng.gen_range(0..key_space.len());
+            let key_idx =
+                sample_key_index(&mut rng, key_distribution, &zipf_cumulative, key_space.len());
+            if rng.gen_bool(range_scan_probability) {
+                // A scan isn't a single-key access, so it sits outside the
+                // per-key dedup below: it's fine for it to overlap a key this
+                // transaction also reads or writes via another op.
+                let span = rng.gen_range(1..=MAX_RANGE_SCAN_SPAN.min(key_space.len()));
+                let (start, end) = range_scan_bounds(&key_space, key_idx, span);
+                ops.push(Operation::RangeScan(RangeScanOperation {
+                    start,
+                    end,
+                    // Filled in by the checker from the transaction's `End`
+                    // output once the scan has actually run.
+                    results: Vec::new(),
+                }));
+                continue;
+            }
             if !keys_used.insert(key_idx) {
                 continue;
             }
@@ -203,7 +361,19 @@ fn main() {
 
     println!("{}", serde_json::to_string(&transactions).unwrap());
 
-    let db_config = config(WORKLOAD_DIR, CACHE_CAPACITY, SEGMENT_SIZE, true);
+    // When `--tempdir` is set, open the database under a fresh `TempDir` whose
+    // guard is held until all worker threads join, so the on-disk state is
+    // removed on scope exit — including on panic — and the harness can be run
+    // repeatedly in CI without leaving `WORKLOAD_DIR` behind.
+    let tempdir = if matches.is_present("tempdir") {
+        Some(tempfile::TempDir::new().unwrap())
+    } else {
+        None
+    };
+    let db_config = match &tempdir {
+        Some(tempdir) => config(tempdir.path(), CACHE_CAPACITY, SEGMENT_SIZE, true),
+        None => config(WORKLOAD_DIR, CACHE_CAPACITY, SEGMENT_SIZE, true),
+    };
     let db = Arc::new(db_config.open().unwrap());
 
     let mut handles = Vec::new();
@@ -238,12 +408,20 @@ fn main() {
                 let serialized = serde_json::to_string(&output).unwrap();
                 println!("{}", serialized);
 
+                // Scan results depend on the database state at the moment the
+                // transaction actually runs, so they're captured here rather than
+                // baked into the spec printed before execution. The closure can
+                // retry on conflict, so clear it on every attempt.
+                let range_scan_results: RefCell<Vec<(usize, Vec<(Vec<u8>, Vec<u8>)>)>> =
+                    RefCell::new(Vec::new());
                 let get_results: Vec<Option<Vec<u8>>> = db
                     .transaction::<_, _, ()>(|tree| {
+                        range_scan_results.borrow_mut().clear();
                         transaction
                             .ops
                             .iter()
-                            .map(|op| {
+                            .enumerate()
+                            .map(|(op_idx, op)| {
                                 Ok(match op {
                                     Operation::Get(GetOperation { key }) => {
                                         tree.get(key)?.map(|value| value.as_ref().to_owned())
@@ -256,11 +434,33 @@ fn main() {
                                         tree.remove(key.clone())?;
                                         None
                                     }
+                                    Operation::RangeScan(RangeScanOperation {
+                                        start, end, ..
+                                    }) => {
+                                        let observed = tree
+                                            .range(start.clone()..end.clone())
+                                            .map(|res| {
+                                                res.map(|(k, v)| {
+                                                    (k.as_ref().to_owned(), v.as_ref().to_owned())
+                                                })
+                                            })
+                                            .collect::<Result<_, _>>()?;
+                                        range_scan_results.borrow_mut().push((op_idx, observed));
+                                        None
+                                    }
                                 })
                             })
                             .collect()
                     })
                     .unwrap();
+                let range_scan_results = range_scan_results.into_inner();
+
+                // sled's transaction return only guarantees the commit is
+                // visible in memory, not fsynced — flush before reporting `End`
+                // so a kill after this point can never lose an acknowledged
+                // transaction and the crash oracle's observed-End-implies-
+                // durable assumption holds.
+                db.flush().unwrap();
 
                 let end_instant = Instant::now();
                 let end = (end_instant - t0).as_nanos();
@@ -268,6 +468,7 @@ fn main() {
                     transaction_idx,
                     end,
                     get_results,
+                    range_scan_results,
                 });
                 let serialized = serde_json::to_string(&output).unwrap();
                 println!("{}", serialized);
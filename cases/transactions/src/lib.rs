@@ -1,3 +1,5 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
 use serde::{Deserialize, Serialize};
 
 pub use common_utils::*;
@@ -5,6 +7,162 @@ pub use common_utils::*;
 pub const SEGMENT_SIZE: usize = 256;
 pub const CACHE_CAPACITY: usize = 256;
 
+/// A fixed-capacity circular buffer backed by a `[Option<T>; N]` array. It never
+/// heap-allocates after construction: pushing onto a full buffer overwrites and
+/// returns the oldest element. This gives the read cache predictable memory use
+/// and no allocator churn on the hot path.
+pub struct FixedVecDeque<T, const N: usize> {
+    buf: [Option<T>; N],
+    /// Index of the front (oldest) element.
+    head: usize,
+    len: usize,
+}
+
+impl<T, const N: usize> Default for FixedVecDeque<T, N> {
+    fn default() -> Self {
+        FixedVecDeque::new()
+    }
+}
+
+impl<T, const N: usize> FixedVecDeque<T, N> {
+    pub fn new() -> FixedVecDeque<T, N> {
+        FixedVecDeque {
+            buf: std::array::from_fn(|_| None),
+            head: 0,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Append to the back. When the buffer is already full the oldest element is
+    /// evicted and returned; otherwise `None` is returned.
+    pub fn push_back(&mut self, value: T) -> Option<T> {
+        if N == 0 {
+            return Some(value);
+        }
+        if self.len == N {
+            let evicted = self.buf[self.head].take();
+            self.buf[self.head] = Some(value);
+            self.head = (self.head + 1) % N;
+            evicted
+        } else {
+            let idx = (self.head + self.len) % N;
+            self.buf[idx] = Some(value);
+            self.len += 1;
+            None
+        }
+    }
+
+    /// Remove and return the oldest element, or `None` if empty.
+    pub fn pop_front(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        let value = self.buf[self.head].take();
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        value
+    }
+
+    /// Iterate from oldest to newest.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        let head = self.head;
+        let len = self.len;
+        (0..len).map(move |i| self.buf[(head + i) % N].as_ref().unwrap())
+    }
+
+    /// The occupied entries as up to two contiguous slices, `(front, back)`,
+    /// where `back` is non-empty only when the live region wraps past the end of
+    /// the backing array. Every returned entry is `Some`.
+    pub fn as_slices(&self) -> (&[Option<T>], &[Option<T>]) {
+        if self.len == 0 {
+            return (&[], &[]);
+        }
+        let tail = self.head + self.len;
+        if tail <= N {
+            (&self.buf[self.head..tail], &[])
+        } else {
+            (&self.buf[self.head..N], &self.buf[0..tail - N])
+        }
+    }
+}
+
+/// An ordered source fragmented into up to two contiguous slices — the shape a
+/// wrapped ring buffer ([`FixedVecDeque::as_slices`]) or a split page presents.
+struct SliceCursor<'a, T> {
+    front: &'a [T],
+    back: &'a [T],
+}
+
+impl<'a, T> SliceCursor<'a, T> {
+    fn new(front: &'a [T], back: &'a [T]) -> SliceCursor<'a, T> {
+        SliceCursor { front, back }
+    }
+
+    /// Rotate `back` into `front` once `front` has been fully consumed, so the
+    /// source presents a single non-empty run until it is exhausted.
+    fn refill(&mut self) {
+        if self.front.is_empty() {
+            self.front = self.back;
+            self.back = &[];
+        }
+    }
+}
+
+/// Zips two ordered sources — each possibly fragmented into `(front, back)`
+/// contiguous slices — into a stream of matching equal-length slice pairs
+/// without materializing an intermediate `Vec`. Each step takes
+/// `n = min(a_front.len(), b_front.len())`, yields the `n`-length heads of both
+/// fronts, advances each front by `n` (rotating `back` into `front` when a front
+/// empties), and stops when either source is exhausted; any uneven remainder is
+/// skipped.
+pub struct ChunkMerge<'a, A, B> {
+    a: SliceCursor<'a, A>,
+    b: SliceCursor<'a, B>,
+}
+
+impl<'a, A, B> ChunkMerge<'a, A, B> {
+    pub fn new(a: (&'a [A], &'a [A]), b: (&'a [B], &'a [B])) -> ChunkMerge<'a, A, B> {
+        ChunkMerge {
+            a: SliceCursor::new(a.0, a.1),
+            b: SliceCursor::new(b.0, b.1),
+        }
+    }
+}
+
+impl<'a, A, B> Iterator for ChunkMerge<'a, A, B> {
+    type Item = (&'a [A], &'a [B]);
+
+    fn next(&mut self) -> Option<(&'a [A], &'a [B])> {
+        self.a.refill();
+        self.b.refill();
+        if self.a.front.is_empty() || self.b.front.is_empty() {
+            return None;
+        }
+        let n = self.a.front.len().min(self.b.front.len());
+        let a_head = &self.a.front[..n];
+        let b_head = &self.b.front[..n];
+        self.a.front = &self.a.front[n..];
+        self.b.front = &self.b.front[n..];
+        Some((a_head, b_head))
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GetOperation {
     pub key: Vec<u8>,
@@ -21,19 +179,34 @@ pub struct RemoveOperation {
     pub key: Vec<u8>,
 }
 
+/// A scan over the half-open key range `[start, end)`, recording every
+/// `(key, value)` pair the scan observed in key order. Unlike a point `Get`, a
+/// range scan also constrains keys that are *absent*, which is what lets the
+/// checker find predicate (phantom) anti-dependencies.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RangeScanOperation {
+    pub start: Vec<u8>,
+    pub end: Vec<u8>,
+    pub results: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub enum Operation {
     Get(GetOperation),
     Insert(InsertOperation),
     Remove(RemoveOperation),
+    RangeScan(RangeScanOperation),
 }
 
 impl Operation {
+    /// A representative key for the operation. For a range scan this is the
+    /// inclusive lower bound of the predicate.
     pub fn key(&self) -> &[u8] {
         match self {
             Operation::Get(GetOperation { key }) => key,
             Operation::Insert(InsertOperation { key, .. }) => key,
             Operation::Remove(RemoveOperation { key }) => key,
+            Operation::RangeScan(RangeScanOperation { start, .. }) => start,
         }
     }
 }
@@ -54,6 +227,13 @@ pub struct TransactionEndOutput {
     pub transaction_idx: usize,
     pub end: u128,
     pub get_results: Vec<Option<Vec<u8>>>,
+    /// `(op_idx, observed)` for every `RangeScan` op in this transaction. A
+    /// scan's result set depends on the database state at the moment the
+    /// transaction actually runs, so it can't be baked into the spec printed
+    /// before execution the way a `Get`'s key can — the consumer backfills
+    /// each scan's `RangeScanOperation::results` from here once the
+    /// transaction completes.
+    pub range_scan_results: Vec<(usize, Vec<(Vec<u8>, Vec<u8>)>)>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -61,3 +241,363 @@ pub enum TransactionOutput {
     Start(TransactionStartOutput),
     End(TransactionEndOutput),
 }
+
+/// A reason a recorded history could not be linearized against the sequential
+/// KV model, reported by [`linearize`].
+#[derive(Debug)]
+pub enum Anomaly {
+    /// No linearization exists. `prefix` is the largest number of operations
+    /// that could be ordered consistently, and `operation` is the transaction
+    /// index at which every remaining ordering diverged from the recorded
+    /// results.
+    NotLinearizable { prefix: usize, operation: usize },
+    /// No serialization consistent with real-time precedence reproduces the
+    /// recorded reads. `prefix` is the largest number of transactions that could
+    /// be placed, and `operation` is the transaction index whose reads could not
+    /// be satisfied by any extension of that prefix — the head of the conflicting
+    /// cycle.
+    NotSerializable { prefix: usize, operation: usize },
+}
+
+/// A single call/return entry in the real-time history: one completed (or
+/// crashed) transaction together with the operations it ran and the values it
+/// observed.
+struct Entry<'a> {
+    transaction_idx: usize,
+    start: u128,
+    /// `None` when the transaction crashed before returning, in which case it
+    /// may or may not have committed.
+    end: Option<u128>,
+    ops: &'a [Operation],
+    get_results: Vec<Option<Vec<u8>>>,
+}
+
+/// Apply an entry's writes to `model` and confirm its reads match, returning the
+/// undo information needed to revert on backtrack. Returns `None` if a read did
+/// not match the model, meaning this entry cannot be linearized here.
+fn try_apply(
+    model: &mut BTreeMap<Vec<u8>, Vec<u8>>,
+    entry: &Entry<'_>,
+) -> Option<Vec<(Vec<u8>, Option<Vec<u8>>)>> {
+    // Within a transaction each key is read xor written at most once, so reads
+    // can all be checked against the pre-state before any writes are applied.
+    for (op_idx, op) in entry.ops.iter().enumerate() {
+        match op {
+            Operation::Get(GetOperation { key }) => {
+                let expected = entry.get_results.get(op_idx).and_then(|r| r.clone());
+                if model.get(key).cloned() != expected {
+                    return None;
+                }
+            }
+            Operation::RangeScan(RangeScanOperation { start, end, results }) => {
+                let observed: Vec<(Vec<u8>, Vec<u8>)> = model
+                    .range(start.clone()..end.clone())
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect();
+                if observed != *results {
+                    return None;
+                }
+            }
+            Operation::Insert(_) | Operation::Remove(_) => {}
+        }
+    }
+    let mut undo = Vec::new();
+    for op in entry.ops {
+        match op {
+            Operation::Get(_) | Operation::RangeScan(_) => {}
+            Operation::Insert(InsertOperation { key, value }) => {
+                undo.push((key.clone(), model.insert(key.clone(), value.clone())));
+            }
+            Operation::Remove(RemoveOperation { key }) => {
+                undo.push((key.clone(), model.remove(key)));
+            }
+        }
+    }
+    Some(undo)
+}
+
+fn undo_apply(model: &mut BTreeMap<Vec<u8>, Vec<u8>>, undo: Vec<(Vec<u8>, Option<Vec<u8>>)>) {
+    for (key, previous) in undo.into_iter().rev() {
+        match previous {
+            Some(value) => {
+                model.insert(key, value);
+            }
+            None => {
+                model.remove(&key);
+            }
+        }
+    }
+}
+
+/// Check whether the recorded concurrent `history` is linearizable against a
+/// sequential key/value model, using the Wing–Gong search: a depth-first
+/// traversal that, at each step, picks a still-pending operation whose return has
+/// not passed any un-linearized operation's call, tentatively applies it to the
+/// model, and recurses, backtracking when the model disagrees with the recorded
+/// `get_results`. Visited `(linearized-set, model)` pairs are memoized to prune
+/// the search — the memo key is the model itself, not a hash of it, so a hash
+/// collision can never prune a branch the search hasn't actually ruled out.
+/// Crashed transactions (no recorded return) may be skipped as
+/// may-or-may-not-have-committed.
+pub fn linearize(specs: &[TransactionSpec], history: &[TransactionOutput]) -> Result<(), Anomaly> {
+    let mut starts: BTreeMap<usize, u128> = BTreeMap::new();
+    let mut ends: BTreeMap<usize, (u128, Vec<Option<Vec<u8>>>)> = BTreeMap::new();
+    for output in history {
+        match output {
+            TransactionOutput::Start(s) => {
+                starts.insert(s.transaction_idx, s.start);
+            }
+            TransactionOutput::End(e) => {
+                ends.insert(e.transaction_idx, (e.end, e.get_results.clone()));
+            }
+        }
+    }
+
+    let mut entries: Vec<Entry<'_>> = Vec::new();
+    for (&idx, &start) in starts.iter() {
+        let (end, get_results) = match ends.get(&idx) {
+            Some((end, results)) => (Some(*end), results.clone()),
+            None => (None, Vec::new()),
+        };
+        entries.push(Entry {
+            transaction_idx: idx,
+            start,
+            end,
+            ops: &specs[idx].ops,
+            get_results,
+        });
+    }
+
+    let n = entries.len();
+    let mut model: BTreeMap<Vec<u8>, Vec<u8>> = BTreeMap::new();
+    let mut linearized: BTreeSet<usize> = BTreeSet::new();
+    let mut visited: HashSet<(BTreeSet<usize>, BTreeMap<Vec<u8>, Vec<u8>>)> = HashSet::new();
+    let mut deepest = 0usize;
+    let mut blocked_operation = 0usize;
+
+    if search(
+        &entries,
+        &mut linearized,
+        &mut model,
+        &mut visited,
+        &mut deepest,
+        &mut blocked_operation,
+    ) {
+        Ok(())
+    } else {
+        Err(Anomaly::NotLinearizable {
+            prefix: deepest,
+            operation: if n == 0 {
+                0
+            } else {
+                entries[blocked_operation.min(n - 1)].transaction_idx
+            },
+        })
+    }
+}
+
+fn search(
+    entries: &[Entry<'_>],
+    linearized: &mut BTreeSet<usize>,
+    model: &mut BTreeMap<Vec<u8>, Vec<u8>>,
+    visited: &mut HashSet<(BTreeSet<usize>, BTreeMap<Vec<u8>, Vec<u8>>)>,
+    deepest: &mut usize,
+    blocked_operation: &mut usize,
+) -> bool {
+    let placed = linearized.len();
+    if placed > *deepest {
+        *deepest = placed;
+    }
+    if placed == entries.len() {
+        return true;
+    }
+    if !visited.insert((linearized.clone(), model.clone())) {
+        return false;
+    }
+
+    // The earliest return time among still-pending operations. Any operation
+    // whose call starts after this instant cannot be linearized next, because a
+    // concurrent operation that already returned must be ordered before it.
+    let min_end = entries
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !linearized.contains(i))
+        .filter_map(|(_, e)| e.end)
+        .min();
+
+    for (i, entry) in entries.iter().enumerate() {
+        if linearized.contains(&i) {
+            continue;
+        }
+        if let Some(min_end) = min_end {
+            if entry.start > min_end {
+                continue;
+            }
+        }
+        *blocked_operation = i;
+
+        // A crashed operation (no return) may be skipped as if it never
+        // committed, by linearizing it with no model effect.
+        if entry.end.is_none() {
+            linearized.insert(i);
+            let found = search(entries, linearized, model, visited, deepest, blocked_operation);
+            linearized.remove(&i);
+            if found {
+                return true;
+            }
+        }
+
+        if let Some(undo) = try_apply(model, entry) {
+            linearized.insert(i);
+            let found = search(entries, linearized, model, visited, deepest, blocked_operation);
+            linearized.remove(&i);
+            if found {
+                return true;
+            }
+            undo_apply(model, undo);
+        }
+    }
+    false
+}
+
+/// Search for a total order over whole transactions that (a) respects real-time
+/// precedence — if transaction A returned before transaction B was invoked, A is
+/// ordered before B — and (b) reproduces every recorded `Get`/`RangeScan`
+/// result when the transactions are applied to a sequential key/value model in
+/// that order. This is the serializability-under-real-time (strict
+/// serializability) check for the history emitted by `transactions_workload`.
+///
+/// The search is depth-first with backtracking over the *frontier*: the set of
+/// not-yet-placed transactions whose real-time predecessors are all already
+/// placed. Each frontier candidate is tentatively applied to the model (which
+/// also checks its reads), recursed on, and undone on backtrack; a candidate is
+/// rejected the moment any of its reads disagrees with the model. On success the
+/// placement order is returned as a list of transaction indices; on failure an
+/// [`Anomaly::NotSerializable`] reports the deepest prefix reached.
+pub fn find_serialization(
+    specs: &[TransactionSpec],
+    history: &[TransactionOutput],
+) -> Result<Vec<usize>, Anomaly> {
+    let mut starts: BTreeMap<usize, u128> = BTreeMap::new();
+    let mut ends: BTreeMap<usize, (u128, Vec<Option<Vec<u8>>>)> = BTreeMap::new();
+    for output in history {
+        match output {
+            TransactionOutput::Start(s) => {
+                starts.insert(s.transaction_idx, s.start);
+            }
+            TransactionOutput::End(e) => {
+                ends.insert(e.transaction_idx, (e.end, e.get_results.clone()));
+            }
+        }
+    }
+
+    let mut entries: Vec<Entry<'_>> = Vec::new();
+    for (&idx, &start) in starts.iter() {
+        let (end, get_results) = match ends.get(&idx) {
+            Some((end, results)) => (Some(*end), results.clone()),
+            None => (None, Vec::new()),
+        };
+        entries.push(Entry {
+            transaction_idx: idx,
+            start,
+            end,
+            ops: &specs[idx].ops,
+            get_results,
+        });
+    }
+
+    // Real-time predecessors: `a` precedes `b` when `a` returned no later than
+    // `b` was invoked. A crashed transaction (no return) imposes no precedence.
+    let n = entries.len();
+    let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for b in 0..n {
+        for a in 0..n {
+            if a == b {
+                continue;
+            }
+            if let Some(a_end) = entries[a].end {
+                if a_end <= entries[b].start {
+                    predecessors[b].push(a);
+                }
+            }
+        }
+    }
+
+    let mut model: BTreeMap<Vec<u8>, Vec<u8>> = BTreeMap::new();
+    let mut placed = vec![false; n];
+    let mut order = Vec::with_capacity(n);
+    let mut deepest = 0usize;
+    let mut blocked = 0usize;
+
+    if serialize_search(
+        &entries,
+        &predecessors,
+        &mut placed,
+        &mut order,
+        &mut model,
+        &mut deepest,
+        &mut blocked,
+    ) {
+        Ok(order.iter().map(|&i| entries[i].transaction_idx).collect())
+    } else {
+        Err(Anomaly::NotSerializable {
+            prefix: deepest,
+            operation: if n == 0 {
+                0
+            } else {
+                entries[blocked.min(n - 1)].transaction_idx
+            },
+        })
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn serialize_search(
+    entries: &[Entry<'_>],
+    predecessors: &[Vec<usize>],
+    placed: &mut [bool],
+    order: &mut Vec<usize>,
+    model: &mut BTreeMap<Vec<u8>, Vec<u8>>,
+    deepest: &mut usize,
+    blocked: &mut usize,
+) -> bool {
+    if order.len() > *deepest {
+        *deepest = order.len();
+    }
+    if order.len() == entries.len() {
+        return true;
+    }
+
+    for i in 0..entries.len() {
+        if placed[i] {
+            continue;
+        }
+        // Only transactions on the frontier — all real-time predecessors placed
+        // — are eligible next.
+        if !predecessors[i].iter().all(|&p| placed[p]) {
+            continue;
+        }
+        *blocked = i;
+        placed[i] = true;
+        order.push(i);
+
+        // A crashed transaction may not have committed; allow placing it with no
+        // effect on the model.
+        if entries[i].end.is_none()
+            && serialize_search(entries, predecessors, placed, order, model, deepest, blocked)
+        {
+            return true;
+        }
+
+        if let Some(undo) = try_apply(model, &entries[i]) {
+            if serialize_search(entries, predecessors, placed, order, model, deepest, blocked) {
+                return true;
+            }
+            undo_apply(model, undo);
+        }
+
+        order.pop();
+        placed[i] = false;
+    }
+    false
+}
@@ -0,0 +1,539 @@
+//! ALICE-style crash-state exploration.
+//!
+//! Rather than firing a SIGKILL at a random moment and hoping `verify` notices
+//! the damage (see [`start_sigkill_timer`](crate::start_sigkill_timer)), this
+//! module drives the workload under `ptrace` with `PTRACE_SYSCALL`, records every
+//! filesystem-modifying syscall sled issues against the workload directory, and
+//! then systematically enumerates the crash states that a realistic storage stack
+//! could leave behind. Each candidate crash state is materialized by replaying a
+//! subset of the recorded "micro-operations" onto a clean snapshot of the
+//! directory, and the per-workload checker is run against it. The first subset
+//! whose checker fails is reported as a crash-consistency vulnerability.
+
+use std::error;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::os::unix::ffi::OsStringExt;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// The sector size used when splitting a large write into independently
+/// persisted micro-operations. Real disks persist in units no larger than this.
+pub const SECTOR_SIZE: usize = 512;
+
+/// A filesystem-modifying syscall observed against the workload directory, in the
+/// order it was issued by the traced child.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FsSyscall {
+    /// A file was created (`creat`, or `open`/`openat` with `O_CREAT`).
+    Create { path: PathBuf },
+    /// Bytes were written at a known offset (`write` at the current file offset,
+    /// `pwrite`, or an appending write).
+    Write {
+        path: PathBuf,
+        offset: u64,
+        data: Vec<u8>,
+    },
+    /// A file was truncated or extended to `len` bytes (`ftruncate`).
+    Truncate { path: PathBuf, len: u64 },
+    /// A file was renamed.
+    Rename { from: PathBuf, to: PathBuf },
+    /// A file was removed (`unlink`).
+    Unlink { path: PathBuf },
+    /// A durability barrier (`fsync`, `fdatasync`, `msync`). Nothing issued
+    /// before the barrier may be reordered past it.
+    Barrier { path: PathBuf },
+}
+
+impl FsSyscall {
+    fn is_barrier(&self) -> bool {
+        matches!(self, FsSyscall::Barrier { .. })
+    }
+}
+
+/// A single unit of persistence: the smallest change that the storage stack
+/// either applies atomically or drops entirely on a crash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MicroOp {
+    Create { path: PathBuf },
+    Write { path: PathBuf, offset: u64, data: Vec<u8> },
+    Truncate { path: PathBuf, len: u64 },
+    Rename { from: PathBuf, to: PathBuf },
+    Unlink { path: PathBuf },
+}
+
+/// The persistence model used to generate candidate crash states. The two axes
+/// are independent, mirroring the ALICE abstract persistence model.
+#[derive(Debug, Clone, Copy)]
+pub struct PersistenceModel {
+    /// When set, a single large `write`/`pwrite` is split into sector-sized
+    /// micro-ops, each of which is independently persisted-or-not.
+    pub atomicity: bool,
+    /// When set, micro-ops falling between two barriers may be applied in any
+    /// order. When clear (strict mode), only ordered prefixes survive.
+    pub reordering: bool,
+    /// The sector size used for the atomicity axis.
+    pub sector_size: usize,
+}
+
+impl Default for PersistenceModel {
+    fn default() -> PersistenceModel {
+        PersistenceModel {
+            atomicity: true,
+            reordering: true,
+            sector_size: SECTOR_SIZE,
+        }
+    }
+}
+
+impl PersistenceModel {
+    /// Split the recorded syscall log into micro-ops, grouped by the barrier
+    /// they fall under. The returned vector has one group per inter-barrier
+    /// epoch; micro-ops within an epoch may be reordered when `reordering` is
+    /// set, while micro-ops in earlier epochs are always applied first.
+    pub fn micro_ops(&self, log: &[FsSyscall]) -> Vec<Vec<MicroOp>> {
+        let mut epochs = Vec::new();
+        let mut current = Vec::new();
+        for syscall in log {
+            if syscall.is_barrier() {
+                if !current.is_empty() {
+                    epochs.push(std::mem::take(&mut current));
+                }
+                continue;
+            }
+            match syscall {
+                FsSyscall::Create { path } => {
+                    current.push(MicroOp::Create { path: path.clone() });
+                }
+                FsSyscall::Write { path, offset, data } => {
+                    if self.atomicity {
+                        let mut split_offset = *offset;
+                        for chunk in data.chunks(self.sector_size) {
+                            current.push(MicroOp::Write {
+                                path: path.clone(),
+                                offset: split_offset,
+                                data: chunk.to_vec(),
+                            });
+                            split_offset += chunk.len() as u64;
+                        }
+                    } else {
+                        current.push(MicroOp::Write {
+                            path: path.clone(),
+                            offset: *offset,
+                            data: data.clone(),
+                        });
+                    }
+                }
+                FsSyscall::Truncate { path, len } => {
+                    current.push(MicroOp::Truncate { path: path.clone(), len: *len });
+                }
+                FsSyscall::Rename { from, to } => {
+                    current.push(MicroOp::Rename { from: from.clone(), to: to.clone() });
+                }
+                FsSyscall::Unlink { path } => {
+                    current.push(MicroOp::Unlink { path: path.clone() });
+                }
+                FsSyscall::Barrier { .. } => unreachable!("barriers handled above"),
+            }
+        }
+        if !current.is_empty() {
+            epochs.push(current);
+        }
+        epochs
+    }
+
+    /// Enumerate candidate crash states as ordered lists of micro-ops to replay
+    /// onto a clean snapshot. Each candidate consists of every micro-op from all
+    /// fully-surviving earlier epochs, plus a prefix (or, in reordering mode, an
+    /// arbitrary subset) of one partially-surviving epoch. A surviving subset is
+    /// replayed in its recorded order; other orderings of it are not generated.
+    pub fn enumerate(&self, epochs: &[Vec<MicroOp>]) -> Vec<Vec<MicroOp>> {
+        let mut candidates = Vec::new();
+        let mut committed: Vec<MicroOp> = Vec::new();
+        for epoch in epochs {
+            if self.reordering {
+                // Every subset of this epoch may survive; each surviving subset
+                // is replayed in recorded order. We bound
+                // the blow-up by enumerating subsets as bitmasks, which caps a
+                // single chunk at 16 ops (2^16 subsets). Epochs larger than that
+                // are split into consecutive 16-op chunks so every op is still
+                // covered, rather than silently dropping the tail; this loses
+                // cross-chunk reorderings but keeps within-chunk ones.
+                let n = epoch.len();
+                if n > 16 {
+                    eprintln!(
+                        "crash_explorer: epoch of {} ops exceeds the 16-op reordering \
+                         bound, splitting into {} chunks",
+                        n,
+                        n.div_ceil(16)
+                    );
+                }
+                for chunk in epoch.chunks(16) {
+                    let chunk_len = chunk.len();
+                    for mask in 0u64..(1u64 << chunk_len) {
+                        let mut candidate = committed.clone();
+                        for (i, op) in chunk.iter().enumerate() {
+                            if mask & (1 << i) != 0 {
+                                candidate.push(op.clone());
+                            }
+                        }
+                        candidates.push(candidate);
+                    }
+                    committed.extend_from_slice(chunk);
+                }
+                continue;
+            } else {
+                // Strict mode: only ordered prefixes of this epoch survive.
+                for prefix_len in 0..=epoch.len() {
+                    let mut candidate = committed.clone();
+                    candidate.extend_from_slice(&epoch[..prefix_len]);
+                    candidates.push(candidate);
+                }
+            }
+            committed.extend_from_slice(epoch);
+        }
+        candidates
+    }
+}
+
+/// Replay a chosen subset of micro-ops onto a fresh copy of `snapshot`,
+/// producing a crashed-state directory at `dest`.
+pub fn materialize(snapshot: &Path, dest: &Path, ops: &[MicroOp]) -> io::Result<()> {
+    copy_dir(snapshot, dest)?;
+    for op in ops {
+        match op {
+            MicroOp::Create { path } => {
+                let target = reroot(snapshot, dest, path);
+                if !target.exists() {
+                    fs::File::create(&target)?;
+                }
+            }
+            MicroOp::Write { path, offset, data } => {
+                use std::io::{Seek, SeekFrom, Write};
+                let target = reroot(snapshot, dest, path);
+                let mut file = fs::OpenOptions::new().write(true).create(true).open(&target)?;
+                file.seek(SeekFrom::Start(*offset))?;
+                file.write_all(data)?;
+            }
+            MicroOp::Truncate { path, len } => {
+                let target = reroot(snapshot, dest, path);
+                let file = fs::OpenOptions::new().write(true).open(&target)?;
+                file.set_len(*len)?;
+            }
+            MicroOp::Rename { from, to } => {
+                fs::rename(reroot(snapshot, dest, from), reroot(snapshot, dest, to))?;
+            }
+            MicroOp::Unlink { path } => {
+                let target = reroot(snapshot, dest, path);
+                if target.exists() {
+                    fs::remove_file(&target)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn reroot(snapshot: &Path, dest: &Path, path: &Path) -> PathBuf {
+    match path.strip_prefix(snapshot) {
+        Ok(rest) => dest.join(rest),
+        Err(_) => dest.join(path.file_name().unwrap_or_else(|| path.as_os_str())),
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Drive the crash explorer: enumerate every candidate crash state under `model`,
+/// materialize it into a scratch directory, and run `checker` against it. The
+/// first crash state whose checker fails is returned as a vulnerability.
+pub fn explore_crash_states<C, E>(
+    model: &PersistenceModel,
+    snapshot: &Path,
+    scratch: &Path,
+    log: &[FsSyscall],
+    checker: C,
+) -> io::Result<Option<Vec<MicroOp>>>
+where
+    C: Fn(&Path) -> Result<(), E>,
+    E: error::Error,
+{
+    let epochs = model.micro_ops(log);
+    for (i, candidate) in model.enumerate(&epochs).into_iter().enumerate() {
+        let dest = scratch.join(format!("crash_state_{}", i));
+        if dest.exists() {
+            fs::remove_dir_all(&dest)?;
+        }
+        materialize(snapshot, &dest, &candidate)?;
+        if let Err(e) = checker(&dest) {
+            eprintln!("crash-consistency vulnerability in crash state {}: {}", i, e);
+            return Ok(Some(candidate));
+        }
+    }
+    Ok(None)
+}
+
+/// Fork the child, run `function` in it under `ptrace`, and return the ordered
+/// log of filesystem-modifying syscalls it issued against a path rooted at
+/// `workload_dir`. The child is stopped at every syscall entry and exit via
+/// `PTRACE_SYSCALL`, so this is Linux-x86_64 specific like the rest of the
+/// scaffolding in this crate.
+pub fn trace_fs_syscalls<F, I, E>(
+    function: F,
+    argument: I,
+    workload_dir: &Path,
+) -> io::Result<Vec<FsSyscall>>
+where
+    F: FnOnce(I) -> Result<(), E>,
+    E: error::Error,
+{
+    let child = unsafe { libc::fork() };
+    if child == 0 {
+        unsafe {
+            libc::ptrace(libc::PTRACE_TRACEME, 0, 0, 0);
+            libc::raise(libc::SIGSTOP);
+        }
+        if let Err(e) = function(argument) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        process::exit(0);
+    } else if child == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut tracer = SyscallTracer::new(child, workload_dir.to_path_buf());
+    tracer.run()?;
+    Ok(tracer.log)
+}
+
+struct SyscallTracer {
+    child: libc::pid_t,
+    workload_dir: PathBuf,
+    log: Vec<FsSyscall>,
+    fds: std::collections::HashMap<i64, PathBuf>,
+}
+
+impl SyscallTracer {
+    fn new(child: libc::pid_t, workload_dir: PathBuf) -> SyscallTracer {
+        SyscallTracer {
+            child,
+            workload_dir,
+            log: Vec::new(),
+            fds: std::collections::HashMap::new(),
+        }
+    }
+
+    fn run(&mut self) -> io::Result<()> {
+        let mut status: libc::c_int = 0;
+        unsafe { libc::waitpid(self.child, &mut status, 0) };
+        unsafe { libc::ptrace(libc::PTRACE_SETOPTIONS, self.child, 0, libc::PTRACE_O_TRACESYSGOOD) };
+
+        loop {
+            if self.wait_for_syscall(&mut status)? {
+                break;
+            }
+            let entry = self.registers()?;
+            if self.wait_for_syscall(&mut status)? {
+                break;
+            }
+            let exit = self.registers()?;
+            self.record(&entry, &exit)?;
+        }
+        Ok(())
+    }
+
+    fn wait_for_syscall(&self, status: &mut libc::c_int) -> io::Result<bool> {
+        unsafe {
+            libc::ptrace(libc::PTRACE_SYSCALL, self.child, 0, 0);
+            libc::waitpid(self.child, status, 0);
+        }
+        Ok(libc::WIFEXITED(*status))
+    }
+
+    fn registers(&self) -> io::Result<libc::user_regs_struct> {
+        let mut regs: libc::user_regs_struct = unsafe { std::mem::zeroed() };
+        let rv = unsafe {
+            libc::ptrace(
+                libc::PTRACE_GETREGS,
+                self.child,
+                0,
+                &mut regs as *mut _ as *mut libc::c_void,
+            )
+        };
+        if rv == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(regs)
+    }
+
+    fn record(
+        &mut self,
+        entry: &libc::user_regs_struct,
+        exit: &libc::user_regs_struct,
+    ) -> io::Result<()> {
+        // x86_64 Linux syscall numbers.
+        const SYS_WRITE: u64 = 1;
+        const SYS_OPEN: u64 = 2;
+        const SYS_PWRITE64: u64 = 18;
+        const SYS_RENAME: u64 = 82;
+        const SYS_FTRUNCATE: u64 = 77;
+        const SYS_FSYNC: u64 = 74;
+        const SYS_FDATASYNC: u64 = 75;
+        const SYS_MSYNC: u64 = 26;
+        const SYS_UNLINK: u64 = 87;
+        const SYS_OPENAT: u64 = 257;
+        const SYS_CREAT: u64 = 85;
+        const O_CREAT: u64 = 0o100;
+
+        let ret = exit.rax as i64;
+        match entry.orig_rax {
+            SYS_OPEN | SYS_OPENAT | SYS_CREAT => {
+                let (path_arg, flags) = match entry.orig_rax {
+                    SYS_OPEN => (entry.rdi, entry.rsi),
+                    SYS_CREAT => (entry.rdi, O_CREAT),
+                    _ => (entry.rsi, entry.rdx),
+                };
+                if let Some(path) = self.read_path(path_arg) {
+                    if self.under_workload(&path) {
+                        if ret >= 0 {
+                            self.fds.insert(ret, path.clone());
+                        }
+                        if flags & O_CREAT != 0 {
+                            self.log.push(FsSyscall::Create { path });
+                        }
+                    }
+                }
+            }
+            SYS_WRITE | SYS_PWRITE64 => {
+                let fd = entry.rdi as i64;
+                if let Some(path) = self.fds.get(&fd).cloned() {
+                    if ret > 0 {
+                        let len = ret as usize;
+                        let offset = if entry.orig_rax == SYS_PWRITE64 {
+                            entry.r10
+                        } else {
+                            // `write` has advanced the file offset past the bytes
+                            // it wrote; read that offset back from procfs.
+                            fs::read_to_string(format!("/proc/{}/fdinfo/{}", self.child, fd))
+                                .ok()
+                                .and_then(|info| {
+                                    info.lines()
+                                        .find_map(|line| line.strip_prefix("pos:"))
+                                        .and_then(|pos| pos.trim().parse::<u64>().ok())
+                                })
+                                .map_or(0, |pos| pos.saturating_sub(len as u64))
+                        };
+                        let data = self.read_memory(entry.rsi, len);
+                        self.log.push(FsSyscall::Write { path, offset, data });
+                    }
+                }
+            }
+            SYS_FTRUNCATE => {
+                let fd = entry.rdi as i64;
+                if let Some(path) = self.fds.get(&fd).cloned() {
+                    self.log.push(FsSyscall::Truncate { path, len: entry.rsi });
+                }
+            }
+            SYS_RENAME => {
+                if let (Some(from), Some(to)) =
+                    (self.read_path(entry.rdi), self.read_path(entry.rsi))
+                {
+                    if self.under_workload(&from) || self.under_workload(&to) {
+                        self.log.push(FsSyscall::Rename { from, to });
+                    }
+                }
+            }
+            SYS_UNLINK => {
+                if let Some(path) = self.read_path(entry.rdi) {
+                    if self.under_workload(&path) {
+                        self.log.push(FsSyscall::Unlink { path });
+                    }
+                }
+            }
+            SYS_FSYNC | SYS_FDATASYNC => {
+                let fd = entry.rdi as i64;
+                if let Some(path) = self.fds.get(&fd).cloned() {
+                    self.log.push(FsSyscall::Barrier { path });
+                }
+            }
+            // `msync` takes a mapped address, not a descriptor, so it is
+            // recorded as a barrier over the whole workload directory.
+            SYS_MSYNC => {
+                self.log.push(FsSyscall::Barrier {
+                    path: self.workload_dir.clone(),
+                });
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn under_workload(&self, path: &Path) -> bool {
+        path.starts_with(&self.workload_dir) || path.is_relative()
+    }
+
+    fn read_path(&self, addr: u64) -> Option<PathBuf> {
+        if addr == 0 {
+            return None;
+        }
+        let mut bytes = Vec::new();
+        let mut offset = 0;
+        loop {
+            let word = unsafe {
+                libc::ptrace(
+                    libc::PTRACE_PEEKDATA,
+                    self.child,
+                    (addr + offset) as *mut libc::c_void,
+                    0,
+                )
+            };
+            let word_bytes = word.to_ne_bytes();
+            for &b in &word_bytes {
+                if b == 0 {
+                    return Some(PathBuf::from(OsString::from_vec(bytes)));
+                }
+                bytes.push(b);
+            }
+            offset += word_bytes.len() as u64;
+            if bytes.len() > libc::PATH_MAX as usize {
+                return Some(PathBuf::from(OsString::from_vec(bytes)));
+            }
+        }
+    }
+
+    fn read_memory(&self, addr: u64, len: usize) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(len);
+        let mut offset = 0u64;
+        while bytes.len() < len {
+            let word = unsafe {
+                libc::ptrace(
+                    libc::PTRACE_PEEKDATA,
+                    self.child,
+                    (addr + offset) as *mut libc::c_void,
+                    0,
+                )
+            };
+            for &b in &word.to_ne_bytes() {
+                if bytes.len() == len {
+                    break;
+                }
+                bytes.push(b);
+            }
+            offset += std::mem::size_of::<libc::c_long>() as u64;
+        }
+        bytes
+    }
+}
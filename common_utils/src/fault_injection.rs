@@ -0,0 +1,139 @@
+//! Deterministic, counter-driven I/O fault injection.
+//!
+//! Whole-process SIGKILL only exercises clean power-loss recovery. Real storage
+//! stacks also return transient errors (`EIO`, `ENOSPC`) and short writes, and
+//! the recovery paths for those are never reached by a random crash. This module
+//! provides the same deterministic fault points sled uses in its own metadata
+//! store: a global call counter is incremented at every fallible I/O site, and
+//! when it reaches the configured "trip point" the call fails synthetically.
+//!
+//! The [`fault_injection_loop`] driver walks the trip point `1, 2, 3, …`,
+//! re-running the workload once per trip point and injecting a failure at exactly
+//! that Nth I/O operation, until the trip point exceeds the number of fallible
+//! calls a clean run makes. This gives reproducible coverage of every reachable
+//! fault site.
+
+use std::error;
+use std::io;
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use rand::Rng;
+
+/// Number of fallible I/O calls made since the last [`reset`].
+static CALL_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// The call index at which the next fallible call should fail. Zero disables
+/// injection.
+static TRIP_POINT: AtomicU64 = AtomicU64::new(0);
+
+/// Arm injection so that the `trip_point`-th fallible call fails. A `trip_point`
+/// of zero disables injection entirely.
+pub fn set_trip_point(trip_point: u64) {
+    TRIP_POINT.store(trip_point, Ordering::SeqCst);
+    CALL_COUNTER.store(0, Ordering::SeqCst);
+}
+
+/// Reset the call counter without changing the trip point. Call this at the start
+/// of each workload run so counts are comparable across runs.
+pub fn reset() {
+    CALL_COUNTER.store(0, Ordering::SeqCst);
+}
+
+/// The number of fallible calls observed since the last [`reset`].
+pub fn calls_made() -> u64 {
+    CALL_COUNTER.load(Ordering::SeqCst)
+}
+
+/// Increment the global call counter and, if this call is the armed trip point,
+/// return the injected error instead of letting the caller proceed. Every
+/// fallible I/O site in a workload should funnel through this.
+pub fn maybe_fail() -> io::Result<()> {
+    let call = CALL_COUNTER.fetch_add(1, Ordering::SeqCst) + 1;
+    let trip = TRIP_POINT.load(Ordering::SeqCst);
+    if trip != 0 && call == trip {
+        Err(io::Error::from_raw_os_error(libc::EIO))
+    } else {
+        Ok(())
+    }
+}
+
+/// Per-call probability (stored as `f64` bits) that a fallible I/O site fails
+/// with `EIO`. Zero disables probabilistic injection, which is the default.
+static FAULT_RATE_BITS: AtomicU64 = AtomicU64::new(0);
+
+/// Set the probability that any individual fallible I/O call fails with `EIO`.
+/// Unlike the deterministic [`set_trip_point`] this fails at random points, so
+/// it is driven straight through a crash loop rather than the counting
+/// [`fault_injection_loop`]. A rate of zero disables injection.
+pub fn set_fault_rate(rate: f64) {
+    FAULT_RATE_BITS.store(rate.to_bits(), Ordering::SeqCst);
+}
+
+/// The currently configured probabilistic fault rate.
+pub fn fault_rate() -> f64 {
+    f64::from_bits(FAULT_RATE_BITS.load(Ordering::SeqCst))
+}
+
+/// Flip a biased coin and, with probability [`fault_rate`], return `EIO` instead
+/// of letting the caller proceed. Wired into the raw `read`/`write` syscalls so a
+/// transient I/O error can land at any point in a run.
+pub fn maybe_fail_random() -> io::Result<()> {
+    let rate = fault_rate();
+    if rate > 0.0 && rand::thread_rng().gen_bool(rate.min(1.0)) {
+        Err(io::Error::from_raw_os_error(libc::EIO))
+    } else {
+        Ok(())
+    }
+}
+
+/// Annotate a fallible I/O site, mirroring sled's `maybe!`/`fallible!` style: the
+/// wrapped expression is only evaluated when this call is not the injected
+/// failure point.
+#[macro_export]
+macro_rules! fallible {
+    ($e:expr) => {{
+        $crate::fault_injection::maybe_fail()?;
+        $e
+    }};
+}
+
+/// Run the workload once per trip point, injecting a fault at each reachable
+/// fallible call in turn and running `verify` after every run. Iteration stops
+/// once the trip point exceeds the number of fallible calls a clean run makes, so
+/// every reachable fault site is covered exactly once.
+pub fn fault_injection_loop<F, V, I, E>(function: F, verify: V, argument: I) -> !
+where
+    F: Fn(&I, bool) -> Result<(), E>,
+    V: Fn(&I) -> Result<(), E>,
+    E: error::Error,
+{
+    // A clean baseline run establishes how many fallible calls the workload
+    // makes, bounding the number of trip points we need to explore.
+    set_trip_point(0);
+    if let Err(e) = function(&argument, false) {
+        eprintln!("baseline run failed before any injection: {}", e);
+        process::exit(1);
+    }
+    let clean_calls = calls_made();
+
+    for trip_point in 1..=clean_calls {
+        set_trip_point(trip_point);
+        match function(&argument, true) {
+            Ok(()) => {}
+            Err(_) => {
+                // The injected error propagated out as expected; the workload
+                // treated it as a crash point. Recovery is checked below.
+            }
+        }
+        set_trip_point(0);
+        if let Err(e) = verify(&argument) {
+            eprintln!(
+                "recovery failed after injecting a fault at I/O call {}: {}",
+                trip_point, e
+            );
+            process::exit(1);
+        }
+    }
+    process::exit(0);
+}
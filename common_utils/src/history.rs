@@ -0,0 +1,119 @@
+//! Streaming, zstd-compressed op-history backing.
+//!
+//! The crash-recovery workloads replay the entire op history to each freshly
+//! forked child. Holding that history in an in-memory `Vec<Op>` makes memory
+//! grow with the run length, capping how long a crash loop can run. This module
+//! persists the history as a series of append-only, newline-framed zstd
+//! segments, one per fork generation and keyed by a monotonically increasing log
+//! sequence number. Because the newline framing lives *inside* the compressed
+//! stream, a decompressed segment can be fed straight into the existing
+//! line-based `OpReader` unchanged — mirroring how sled's own metadata store
+//! snapshots its log under zstd.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+use zstd::stream::read::Decoder;
+use zstd::stream::write::Encoder;
+
+const ZSTD_LEVEL: i32 = 3;
+
+fn segment_path(dir: &Path, lsn: u64) -> PathBuf {
+    dir.join(format!("history.{:020}.zst", lsn))
+}
+
+/// Append-only writer for a single history segment (one fork generation). Each
+/// segment is a self-contained zstd frame; the frame footer is written when the
+/// writer is dropped, so the segment is only complete once its generation ends.
+pub struct HistorySegmentWriter {
+    encoder: Option<Encoder<'static, File>>,
+}
+
+impl HistorySegmentWriter {
+    /// Create (truncating) the segment file for `lsn` under `dir`.
+    pub fn create<P: AsRef<Path>>(dir: P, lsn: u64) -> io::Result<HistorySegmentWriter> {
+        fs::create_dir_all(dir.as_ref())?;
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(segment_path(dir.as_ref(), lsn))?;
+        let encoder = Encoder::new(file, ZSTD_LEVEL)?;
+        Ok(HistorySegmentWriter {
+            encoder: Some(encoder),
+        })
+    }
+
+    /// Append one already-encoded op, adding the trailing newline framing.
+    pub fn append_line(&mut self, encoded: &[u8]) -> io::Result<()> {
+        let encoder = self.encoder.as_mut().unwrap();
+        encoder.write_all(encoded)?;
+        encoder.write_all(b"\n")
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.encoder.as_mut().unwrap().flush()
+    }
+}
+
+impl Drop for HistorySegmentWriter {
+    fn drop(&mut self) {
+        if let Some(encoder) = self.encoder.take() {
+            let _ = encoder.finish();
+        }
+    }
+}
+
+/// Streams every history segment with a log sequence number below `up_to`, in
+/// lsn order, as one decompressed newline-framed byte stream. Missing segments
+/// are skipped, so a gap left by a generation that recorded nothing is harmless.
+pub struct HistoryReader {
+    dir: PathBuf,
+    next_lsn: u64,
+    up_to: u64,
+    current: Option<Decoder<'static, BufReader<File>>>,
+}
+
+impl HistoryReader {
+    pub fn new<P: AsRef<Path>>(dir: P, up_to: u64) -> HistoryReader {
+        HistoryReader {
+            dir: dir.as_ref().to_path_buf(),
+            next_lsn: 0,
+            up_to,
+            current: None,
+        }
+    }
+
+    fn advance(&mut self) -> io::Result<bool> {
+        while self.next_lsn < self.up_to {
+            let path = segment_path(&self.dir, self.next_lsn);
+            self.next_lsn += 1;
+            match File::open(&path) {
+                Ok(file) => {
+                    self.current = Some(Decoder::new(file)?);
+                    return Ok(true);
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(false)
+    }
+}
+
+impl Read for HistoryReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.current.is_none() && !self.advance()? {
+                return Ok(0);
+            }
+            let read = self.current.as_mut().unwrap().read(buf)?;
+            if read == 0 {
+                self.current = None;
+                continue;
+            }
+            return Ok(read);
+        }
+    }
+}
@@ -14,6 +14,11 @@ use rand::Rng;
 pub use sled;
 use sled::Config;
 
+pub mod crash_explorer;
+pub mod fault_injection;
+pub mod history;
+pub mod op_log;
+
 pub const WORKLOAD_DIR: &str = "workload_dir";
 
 pub fn config<P: AsRef<Path>>(
@@ -46,6 +51,141 @@ pub fn start_sigkill_timer() {
     });
 }
 
+/// Raise the soft open-file-descriptor limit toward the hard limit so the crash
+/// loop can fork many children — each allocating several pipe FDs plus sled's
+/// own handles — without tripping `EMFILE` in `Pipe::setup`. On Darwin the soft
+/// limit may not exceed the `kern.maxfilesperproc` sysctl, so it is capped
+/// there. Any failure is ignored: this is a best-effort bump, and platforms
+/// that don't support it simply keep their existing limit.
+pub fn raise_fd_limit() {
+    unsafe {
+        let mut limit: libc::rlimit = std::mem::zeroed();
+        if libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) != 0 {
+            return;
+        }
+        let mut target = limit.rlim_max;
+
+        #[cfg(target_os = "macos")]
+        {
+            let mut max_files: libc::c_int = 0;
+            let mut size = std::mem::size_of::<libc::c_int>();
+            let name = b"kern.maxfilesperproc\0";
+            let rv = libc::sysctlbyname(
+                name.as_ptr() as *const libc::c_char,
+                &mut max_files as *mut _ as *mut libc::c_void,
+                &mut size,
+                std::ptr::null_mut(),
+                0,
+            );
+            if rv == 0 && (max_files as libc::rlim_t) < target {
+                target = max_files as libc::rlim_t;
+            }
+        }
+
+        if target > limit.rlim_cur {
+            limit.rlim_cur = target;
+            let _ = libc::setrlimit(libc::RLIMIT_NOFILE, &limit);
+        }
+    }
+}
+
+/// Selects how and when the crash loop delivers `SIGKILL` to the forked child.
+///
+/// `RandomTimer` reproduces the legacy behavior: the child arms a timer that
+/// fires at a random moment (see [`start_sigkill_timer`]). The `AfterNth*`
+/// variants are deterministic: the parent `ptrace`s the child, counts
+/// durability-relevant syscalls, and kills it after exactly the Nth one, so a
+/// failing seed can be replayed and shrunk down to the minimal number of
+/// persisted operations. `N` is seeded and advanced each loop iteration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrashTrigger {
+    RandomTimer,
+    AfterNthWrite(u64),
+    AfterNthFsync(u64),
+}
+
+impl CrashTrigger {
+    /// The trigger to use on loop `iteration` (0-based): deterministic triggers
+    /// advance their count by one each iteration so successive runs crash one
+    /// syscall later, exhaustively covering every crash point.
+    pub fn at_iteration(self, iteration: u64) -> CrashTrigger {
+        match self {
+            CrashTrigger::RandomTimer => CrashTrigger::RandomTimer,
+            CrashTrigger::AfterNthWrite(n) => CrashTrigger::AfterNthWrite(n + iteration),
+            CrashTrigger::AfterNthFsync(n) => CrashTrigger::AfterNthFsync(n + iteration),
+        }
+    }
+}
+
+/// `ptrace`-drive a freshly forked child, counting durability-relevant syscalls,
+/// and deliver `SIGKILL` after exactly the Nth one selected by `trigger`. Returns
+/// once the child has stopped (killed or exited). x86_64 Linux specific, like the
+/// rest of the scaffolding here.
+fn drive_with_crash_trigger(child: libc::pid_t, trigger: CrashTrigger) {
+    const SYS_WRITE: u64 = 1;
+    const SYS_PWRITE64: u64 = 18;
+    const SYS_FSYNC: u64 = 74;
+    const SYS_FDATASYNC: u64 = 75;
+
+    let target = match trigger {
+        CrashTrigger::RandomTimer => return,
+        CrashTrigger::AfterNthWrite(n) | CrashTrigger::AfterNthFsync(n) => n,
+    };
+
+    let mut status: libc::c_int = 0;
+    unsafe {
+        libc::ptrace(libc::PTRACE_SEIZE, child, 0, libc::PTRACE_O_TRACESYSGOOD);
+        libc::ptrace(libc::PTRACE_INTERRUPT, child, 0, 0);
+        libc::waitpid(child, &mut status, 0);
+    }
+
+    let mut count = 0u64;
+    let mut at_entry = true;
+    loop {
+        unsafe {
+            libc::ptrace(libc::PTRACE_SYSCALL, child, 0, 0);
+            if libc::waitpid(child, &mut status, 0) == -1 || libc::WIFEXITED(status) {
+                return;
+            }
+        }
+        // Count at syscall exit, so the Nth syscall has completed before the kill.
+        if !at_entry {
+            let mut regs: libc::user_regs_struct = unsafe { std::mem::zeroed() };
+            let rv = unsafe {
+                libc::ptrace(
+                    libc::PTRACE_GETREGS,
+                    child,
+                    0,
+                    &mut regs as *mut _ as *mut libc::c_void,
+                )
+            };
+            if rv != -1 {
+                let relevant = match trigger {
+                    CrashTrigger::AfterNthWrite(_) => {
+                        regs.orig_rax == SYS_WRITE || regs.orig_rax == SYS_PWRITE64
+                    }
+                    CrashTrigger::AfterNthFsync(_) => {
+                        regs.orig_rax == SYS_FSYNC || regs.orig_rax == SYS_FDATASYNC
+                    }
+                    CrashTrigger::RandomTimer => false,
+                };
+                if relevant {
+                    count += 1;
+                    if count == target {
+                        unsafe {
+                            libc::kill(child, libc::SIGKILL);
+                            libc::ptrace(libc::PTRACE_CONT, child, 0, libc::SIGKILL);
+                            libc::waitpid(child, &mut status, 0);
+                        }
+                        return;
+                    }
+                }
+            }
+        }
+        at_entry = !at_entry;
+    }
+}
+
 /// This function provides the scaffolding and unsafe libc calls required for a crash
 /// recovery test. It takes a function to be called in the forked child process, an
 /// argument to be passed to that function, and a boolean indicating whether the crash
@@ -76,6 +216,38 @@ pub fn crash_recovery_loop_with_hooks<
     teardown: T,
     argument: I,
     crash: bool,
+) -> ! {
+    crash_recovery_loop_with_trigger(
+        setup,
+        function,
+        parent_after_fork,
+        teardown,
+        argument,
+        crash,
+        CrashTrigger::RandomTimer,
+    )
+}
+
+/// This is the same as `crash_recovery_loop_with_hooks`, but the crash is delivered
+/// according to `trigger`. With `CrashTrigger::RandomTimer` the child is expected to arm
+/// its own timer (the legacy behavior); with an `AfterNth*` trigger the parent instead
+/// `ptrace`s the child and kills it after a precise, repeatable syscall count, advancing
+/// the count each loop iteration so successive runs crash one syscall later.
+pub fn crash_recovery_loop_with_trigger<
+    S: Fn(),
+    F: Fn(I, bool) -> Result<(), E>,
+    P: Fn(),
+    T: Fn(),
+    I,
+    E: error::Error,
+>(
+    setup: S,
+    function: F,
+    parent_after_fork: P,
+    teardown: T,
+    argument: I,
+    crash: bool,
+    trigger: CrashTrigger,
 ) -> ! {
     if !crash {
         if let Err(e) = function(argument, false) {
@@ -85,6 +257,7 @@ pub fn crash_recovery_loop_with_hooks<
             process::exit(0);
         }
     }
+    let mut iteration = 0u64;
     loop {
         setup();
         let child = unsafe { libc::fork() };
@@ -104,6 +277,8 @@ pub fn crash_recovery_loop_with_hooks<
             process::exit(1);
         } else {
             parent_after_fork();
+            drive_with_crash_trigger(child, trigger.at_iteration(iteration));
+            iteration += 1;
             let mut status: libc::c_int = 0;
             let rv = unsafe { libc::waitpid(child, &mut status as *mut libc::c_int, 0) };
             if rv == -1 {